@@ -1,4 +1,5 @@
 use crate::indexer;
+use crate::sql_engine;
 use colored::Colorize;
 use std::path::{Path, PathBuf};
 
@@ -23,7 +24,7 @@ pub async fn execute_command_legacy(
             return Ok(String::new());
         }
         Command::SqlQuery { query } => {
-            return crate::sql_engine::execute_sql_query(file_system_state, &query);
+            return crate::sql_engine::execute_sql_query(file_system_state, favorites_manager, &query).map(|v| v.to_string());
         }
         Command::Docs { command_name } => {
             return crate::docs::show_docs(command_name);
@@ -255,6 +256,18 @@ pub async fn execute_command_legacy(
         Command::FavRm { index: _index } => execute_remove_fav(_index, favorites_manager),
         Command::FavSet => execute_fav_set(file_system_state, favorites_manager),
         Command::RunFav { index: _index } => execute_run_fav(_index, favorites_manager),
+
+        // Index Commands: paths take ~ and $VAR like they do in SQL queries
+        Command::IndexAdd { directory, hashes } => {
+            indexer::add_root(&sql_engine::expand_path(&directory, file_system_state.get_current_path()), hashes)
+        }
+        Command::IndexRemove { directory } => {
+            indexer::remove_root(&sql_engine::expand_path(&directory, file_system_state.get_current_path()))
+        }
+        Command::IndexRebuild { directory } => indexer::rebuild(
+            directory.map(|d| sql_engine::expand_path(&d, file_system_state.get_current_path())).as_deref(),
+        ),
+        Command::IndexStatus => Ok(String::new()), // Handled in commands_ext.rs
         Command::Pipe { .. } => Ok(String::new()), // Handled in shell.rs
         Command::Filter { .. } | Command::SelectFields { .. } => Ok(String::new()),
     }
@@ -317,6 +330,7 @@ pub fn execute_list_all_cmd() -> Result<String, String> {
         "State Commands:",
         "Favorites Commands:",
         "Search Commands:",
+        "Index Commands:",
     ];
 
     let meta_commands = [
@@ -389,6 +403,12 @@ pub fn execute_list_all_cmd() -> Result<String, String> {
             "Runs the file at the index of the Favorites list",
         ),
     ];
+    let index_commands = [
+        ("INDEX ADD <dir> [WITH HASH]", "Keeps file metadata (and hashes) of <dir> for fast SQL"),
+        ("INDEX REMOVE <dir>", "Stops indexing <dir>"),
+        ("INDEX REBUILD [<dir>]", "Brings the index up to date"),
+        ("INDEX STATUS", "Lists indexed directories"),
+    ];
 
     crate::cprintln!("\n{}\n{}", titles[0], titles[1]);
 
@@ -432,6 +452,11 @@ pub fn execute_list_all_cmd() -> Result<String, String> {
         crate::cprintln!("{} : {}", command.bright_magenta(), description);
     }
 
+    crate::cprintln!("\n{}", titles[10]);
+    for (command, description) in index_commands.iter() {
+        crate::cprintln!("{} : {}", command.bright_yellow(), description);
+    }
+
     crate::cprintln!();
 
     Ok(String::new())
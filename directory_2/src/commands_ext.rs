@@ -29,6 +29,12 @@ pub async fn execute_command(
         Command::Env => {
             execute_env_structured()
         }
+        Command::SqlQuery { query } => {
+            crate::sql_engine::execute_sql_query(file_system_state, favorites_manager, &query)
+        }
+        Command::IndexStatus => {
+            execute_index_status_structured()
+        }
         // Fallback to legacy strings wrapped in Value
         other => {
             let res = execute_command_legacy(other, file_system_state, favorites_manager).await?;
@@ -96,7 +102,7 @@ fn execute_list_directory_structured(
         rows.push(row);
     }
 
-    Ok(Value::Table(rows))
+    Ok(Value::Table(rows, Vec::new()))
 }
 
 fn execute_env_structured() -> Result<Value, String> {
@@ -107,6 +113,26 @@ fn execute_env_structured() -> Result<Value, String> {
     Ok(Value::Record(map))
 }
 
+fn execute_index_status_structured() -> Result<Value, String> {
+    let mut rows = Vec::new();
+    for status in crate::indexer::status()? {
+        let updated = chrono::DateTime::from_timestamp(status.updated, 0)
+            .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        let mut row = HashMap::new();
+        row.insert("Root".to_string(), Value::String(status.root.to_string_lossy().to_string()));
+        row.insert("Entries".to_string(), Value::Integer(status.entries as i64));
+        row.insert("Hashed".to_string(), Value::Integer(status.hashed as i64));
+        row.insert("Updated".to_string(), Value::String(updated));
+        row.insert("Size".to_string(), Value::Integer(status.bytes as i64));
+        rows.push(row);
+    }
+    if rows.is_empty() {
+        return Ok(Value::String("Nothing is indexed. Use INDEX ADD <dir> to index a directory".to_string()));
+    }
+    Ok(Value::Table(rows, Vec::new()))
+}
+
 fn execute_list_commands_structured() -> Result<Value, String> {
     let mut rows = Vec::new();
 
@@ -182,6 +208,13 @@ fn execute_list_commands_structured() -> Result<Value, String> {
         ),
     ];
 
+    let index_commands = [
+        ("INDEX ADD <dir> [WITH HASH]", "Keeps file metadata (and hashes) of <dir> for fast SQL"),
+        ("INDEX REMOVE <dir>", "Stops indexing <dir>"),
+        ("INDEX REBUILD [<dir>]", "Brings the index up to date"),
+        ("INDEX STATUS", "Lists indexed directories"),
+    ];
+
     let mut add_commands = |category: &str, cmds: &[(&str, &str)]| {
         for (cmd, desc) in cmds {
             let mut row = HashMap::new();
@@ -200,14 +233,15 @@ fn execute_list_commands_structured() -> Result<Value, String> {
     add_commands("State", &state_commands);
     add_commands("Favorites", &fav_commands);
     add_commands("Search", &search_commands);
+    add_commands("Index", &index_commands);
 
-    Ok(Value::Table(rows))
+    Ok(Value::Table(rows, Vec::new()))
 }
 
 fn execute_filter(input: Option<Value>, column: &str, operator: &str, value: &str) -> Result<Value, String> {
     let input = input.ok_or_else(|| "FILTER requires an input pipeline".to_string())?;
     match input {
-        Value::Table(rows) => {
+        Value::Table(rows, columns) => {
             let mut filtered = Vec::new();
             for row in rows {
                 // Find matching column case-insensitively
@@ -244,7 +278,7 @@ fn execute_filter(input: Option<Value>, column: &str, operator: &str, value: &st
                     }
                 }
             }
-            Ok(Value::Table(filtered))
+            Ok(Value::Table(filtered, columns))
         }
         _ => Err("FILTER only works on Tables".to_string()),
     }
@@ -253,7 +287,12 @@ fn execute_filter(input: Option<Value>, column: &str, operator: &str, value: &st
 fn execute_select_fields(input: Option<Value>, fields: Vec<String>) -> Result<Value, String> {
     let input = input.ok_or_else(|| "SELECT requires an input pipeline".to_string())?;
     match input {
-        Value::Table(rows) => {
+        Value::Table(rows, _) => {
+            // Columns come out in the order the fields were asked for
+            let columns = fields
+                .iter()
+                .filter_map(|field| rows.iter().flat_map(|row| row.keys()).find(|k| k.eq_ignore_ascii_case(field)).cloned())
+                .collect();
             let mut selected = Vec::new();
             for row in rows {
                 let mut new_row = HashMap::new();
@@ -268,7 +307,7 @@ fn execute_select_fields(input: Option<Value>, fields: Vec<String>) -> Result<Va
                 }
                 selected.push(new_row);
             }
-            Ok(Value::Table(selected))
+            Ok(Value::Table(selected, columns))
         }
         _ => Err("SELECT only works on Tables".to_string()),
     }
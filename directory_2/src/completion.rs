@@ -65,7 +65,7 @@ impl Completer for Dir2Helper {
                 "S ", "FAV ", "RF ", "SV ", "LS ", "DS ", "RS ",
                 "EXPORT ", "UNSET ", "ENV ", "ECHO ", "ALIAS ", "UNALIAS ", "ALIASES ",
                 "JOBS ", "FG ", "KILL ", "SELECT ", "LC ", "CLS ", "DOCS ", "EXIT ",
-                "PIPE "
+                "PIPE ", "INDEX "
             ];
             
             let word = &line[start..pos];
@@ -0,0 +1,139 @@
+// Readers for data files queried with `FROM csv('file')`, `json('file')` and
+// `jsonl('file')`. Each turns the file's text into columns and rows for the SQL engine.
+
+use crate::sql_engine::{Row, TypedValue};
+
+/// Reads CSV text. Each column gets one type for all its cells: integer or float
+/// numbers, booleans, or text; empty cells are NULL. Without a header row the
+/// columns are named c1, c2, ...
+pub(crate) fn parse_csv(text: &str, delimiter: u8, header: bool) -> Result<(Vec<String>, Vec<Row>), String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(header)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let mut columns: Vec<String> = if header {
+        reader.headers().map_err(|e| e.to_string())?.iter().map(|h| h.trim().to_string()).collect()
+    } else {
+        Vec::new()
+    };
+
+    let mut records = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        records.push(record.iter().map(str::to_string).collect::<Vec<_>>());
+    }
+    let width = records.iter().map(Vec::len).max().unwrap_or(0);
+    while columns.len() < width {
+        columns.push(format!("c{}", columns.len() + 1));
+    }
+
+    let kinds: Vec<CsvKind> = (0..columns.len())
+        .map(|i| CsvKind::infer(records.iter().filter_map(|r| r.get(i)).map(|cell| cell.trim())))
+        .collect();
+    let rows = records
+        .into_iter()
+        .map(|record| {
+            columns
+                .iter()
+                .zip(&kinds)
+                .enumerate()
+                .map(|(i, (column, kind))| (column.clone(), kind.parse(record.get(i).map_or("", |c| c.trim()))))
+                .collect()
+        })
+        .collect();
+    Ok((columns, rows))
+}
+
+/// The type inferred for a CSV column.
+#[derive(Clone, Copy, PartialEq)]
+enum CsvKind {
+    Number,
+    Boolean,
+    Text,
+}
+
+impl CsvKind {
+    fn infer<'a>(cells: impl Iterator<Item = &'a str>) -> CsvKind {
+        let mut kind = None;
+        for cell in cells.filter(|c| !c.is_empty()) {
+            let cell_kind = if cell.parse::<f64>().is_ok() {
+                CsvKind::Number
+            } else if cell.eq_ignore_ascii_case("true") || cell.eq_ignore_ascii_case("false") {
+                CsvKind::Boolean
+            } else {
+                return CsvKind::Text;
+            };
+            match kind {
+                None => kind = Some(cell_kind),
+                Some(k) if k != cell_kind => return CsvKind::Text,
+                _ => {}
+            }
+        }
+        kind.unwrap_or(CsvKind::Text)
+    }
+
+    fn parse(self, cell: &str) -> TypedValue {
+        match self {
+            _ if cell.is_empty() => TypedValue::Null,
+            CsvKind::Number => cell.parse().map_or(TypedValue::Null, TypedValue::Number),
+            CsvKind::Boolean => TypedValue::Boolean(cell.eq_ignore_ascii_case("true")),
+            CsvKind::Text => TypedValue::Text(cell.to_string()),
+        }
+    }
+}
+
+/// Reads a JSON document: an array of objects becomes one row per object, a
+/// single object becomes one row and an array of scalars a single `value` column.
+pub(crate) fn parse_json(text: &str) -> Result<(Vec<String>, Vec<Row>), String> {
+    match serde_json::from_str(text).map_err(|e| e.to_string())? {
+        serde_json::Value::Array(items) => Ok(json_rows(items)),
+        other => Ok(json_rows(vec![other])),
+    }
+}
+
+/// Reads JSON Lines: one JSON value per non-empty line.
+pub(crate) fn parse_jsonl(text: &str) -> Result<(Vec<String>, Vec<Row>), String> {
+    let mut items = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        items.push(serde_json::from_str(line).map_err(|e| format!("line {}: {}", i + 1, e))?);
+    }
+    Ok(json_rows(items))
+}
+
+/// Turns JSON values into rows. Columns are the union of the object keys in
+/// first-seen order; missing keys are NULL and nested values stay JSON text.
+fn json_rows(items: Vec<serde_json::Value>) -> (Vec<String>, Vec<Row>) {
+    let mut columns: Vec<String> = Vec::new();
+    let mut rows = Vec::new();
+    for item in items {
+        let fields = match item {
+            serde_json::Value::Object(map) => map.into_iter().collect(),
+            scalar => vec![("value".to_string(), scalar)],
+        };
+        let mut row = Row::new();
+        for (key, value) in fields {
+            if !columns.contains(&key) {
+                columns.push(key.clone());
+            }
+            let value = match value {
+                serde_json::Value::Null => TypedValue::Null,
+                serde_json::Value::Bool(b) => TypedValue::Boolean(b),
+                serde_json::Value::Number(n) => n.as_f64().map_or(TypedValue::Null, TypedValue::Number),
+                serde_json::Value::String(s) => TypedValue::Text(s),
+                nested => TypedValue::Text(nested.to_string()),
+            };
+            row.insert(key, value);
+        }
+        rows.push(row);
+    }
+    for row in &mut rows {
+        for column in &columns {
+            row.entry(column.clone()).or_insert(TypedValue::Null);
+        }
+    }
+    (columns, rows)
+}
@@ -90,6 +90,18 @@ pub fn show_docs(command_name: Option<String>) -> Result<String, String> {
                 crate::cprintln!("Usage: RF <id>");
                 crate::cprintln!("Example: RF 1");
             }
+            // Index Commands
+            "INDEX" => {
+                crate::cprintln!("{}", "Command: INDEX (Metadata Index)".bright_yellow().bold());
+                crate::cprintln!("Description: Keeps the metadata of large directory trees in .directory_2/index (next to favorites) so SQL queries over them skip walking the tree and re-hashing unchanged files.");
+                crate::cprintln!("SELECTs under an indexed directory use it automatically, re-checking each entry's size and times so edited files are never stale. DELETE and UPDATE always walk.");
+                crate::cprintln!("Subcommands:");
+                crate::cprintln!("  INDEX ADD <dir> [WITH HASH] : Indexes <dir>, optionally storing SHA-256 hashes for the hash column.");
+                crate::cprintln!("  INDEX REMOVE <dir>          : Deletes the index of <dir>.");
+                crate::cprintln!("  INDEX REBUILD [<dir>]       : Re-reads every indexed directory (or <dir>), re-hashing only changed files.");
+                crate::cprintln!("  INDEX STATUS                : Lists indexed directories, their entry counts and last update.");
+                crate::cprintln!("Example: INDEX ADD ~/work/monorepo WITH HASH");
+            }
             // Search Commands
             "S" | "SEARCH" => {
                 crate::cprintln!("{}", "Command: S / SEARCH".bright_magenta().bold());
@@ -160,6 +172,67 @@ pub fn show_docs(command_name: Option<String>) -> Result<String, String> {
                 crate::cprintln!("  SELECT * FROM .           : Selects all files in current directory");
                 crate::cprintln!("  SELECT <file> FROM .      : Selects a specific file");
             }
+            "SQL" | "QUERY" => {
+                crate::cprintln!("{}", "Command: SQL Queries".yellow().bold());
+                crate::cprintln!("Description: Runs a SQL query over the filesystem and returns a table that can be piped like any other structured command.");
+                crate::cprintln!("Tables:");
+                crate::cprintln!("  files                              : Entries of the current directory");
+                crate::cprintln!("  tree('<path>', <depth>, <follow>)  : Every entry below <path>, optionally depth-limited and following symlinks");
+                crate::cprintln!("  '<dir>' | ~/dir | ../dir | $VAR    : Entries of any other directory");
+                crate::cprintln!("  lines(<table>, <binary>)           : path, relative_path, name, line_no, text of every text file in a table");
+                crate::cprintln!("  csv('<file>', <delimiter>, <header>): rows of a CSV file, column types inferred (no header: c1, c2, ...)");
+                crate::cprintln!("  json('<file>'), jsonl('<file>')    : rows of a JSON array or a JSON Lines file, one column per key");
+                crate::cprintln!("  processes                          : pid, ppid, name, state, owner, uid, threads, cpu, mem (%), rss, vsz (KB), started, command");
+                crate::cprintln!("  mounts                             : device, path, fs_type, options, size, used, available (bytes), use_percent");
+                crate::cprintln!("  env | aliases | interactive        : environment variables, ALIAS definitions, commands run as interactive");
+                crate::cprintln!("  favorites | jobs | history         : FAV list (id as in RUN FAV), background jobs, commands entered this session");
+                crate::cprintln!("  views | <view>                     : saved views (name, columns, query), or the rows of one of them");
+                crate::cprintln!("  <table> a [INNER|LEFT|RIGHT|FULL|CROSS] JOIN <table> b ON ... : columns are named a.<column>, b.<column>");
+                crate::cprintln!("Columns: name, ext, size, modified, is_dir, path, relative_path, parent, depth,");
+                crate::cprintln!("         mode, perms, uid, gid, owner, group, inode, nlink, accessed, created, changed,");
+                crate::cprintln!("         is_symlink, link_target, is_executable");
+                crate::cprintln!("         hash (SHA-256), sha256, blake3: computed only when used, cached between queries");
+                crate::cprintln!("Clauses: WITH name AS (SELECT ...), SELECT DISTINCT, WHERE, GROUP BY, HAVING, ORDER BY, LIMIT, OFFSET");
+                crate::cprintln!("Subqueries: x [NOT] IN (SELECT ...), (SELECT MAX(size) ...), [NOT] EXISTS (SELECT ...), FROM (SELECT ...) t");
+                crate::cprintln!("Operators: =, !=, <>, <, <=, >, >=, AND, OR, NOT, [NOT] LIKE, ILIKE, GLOB, REGEXP, [NOT] IN (...), [NOT] BETWEEN x AND y, IS [NOT] NULL");
+                crate::cprintln!("Aggregates: COUNT, SUM, AVG, MIN, MAX");
+                crate::cprintln!("Functions: LOWER, UPPER, LENGTH, SUBSTR, REPLACE, CONCAT, STEM, PARENT, HUMAN_SIZE, ROUND; + - * / % ||");
+                crate::cprintln!("Dates: NOW(), CURRENT_DATE, DATE(t), AGE(t), t - INTERVAL '7 days', t >= '2026-01-01'");
+                crate::cprintln!("Changes: DELETE FROM <table> WHERE ... [CASCADE] (lists matches and asks first; CASCADE allows directories)");
+                crate::cprintln!("         UPDATE <table> SET name|ext|parent|mode = ... WHERE ... (previews renames, moves and chmods; refuses collisions)");
+                crate::cprintln!("         INSERT INTO <dir> (name|relative_path, is_dir, content, mode) VALUES (...) | SELECT ... (never overwrites)");
+                crate::cprintln!("         Prefix with DRY RUN or EXPLAIN to only report what would change");
+                crate::cprintln!("Plans: EXPLAIN SELECT ... shows the directories walked, depth limits, skipped subtrees, subqueries and content columns read, without reading anything");
+                crate::cprintln!("       depth <= N and path/relative_path LIKE 'dir/%', GLOB 'dir/*' or = '...' stop the walk early");
+                crate::cprintln!("       Directories added with INDEX ADD are read from the index instead of walked (DOCS INDEX)");
+                crate::cprintln!("       Trees are walked on DIR2_THREADS threads (default: one per CPU, e.g. EXPORT DIR2_THREADS=4); Ctrl-C cancels a query");
+                crate::cprintln!("Views: CREATE [OR REPLACE] VIEW name [(columns)] AS SELECT ... saves a query in .directory_2/views.json, next to favorites");
+                crate::cprintln!("       SELECT ... FROM name runs it again (WITH queries of the same name win); DROP VIEW [IF EXISTS] name, ... forgets it");
+                crate::cprintln!("STATE: SELECT path FROM ... INTO STATE [APPEND] loads the selected paths into STATE for RUN STATE, META STATE and FAV SET STATE");
+                crate::cprintln!("Examples:");
+                crate::cprintln!("  SELECT name, size FROM files ORDER BY size DESC LIMIT 20");
+                crate::cprintln!("  SELECT ext, COUNT(*), HUMAN_SIZE(SUM(size)) AS total FROM tree('src') GROUP BY ext");
+                crate::cprintln!("  SELECT name, ROUND(size / 1024.0 / 1024, 1) AS mb FROM tree('.') WHERE size > 10 * 1024 * 1024");
+                crate::cprintln!("  SELECT path FROM tree('.') WHERE name GLOB '*.rs'");
+                crate::cprintln!("  SELECT relative_path, line_no, text FROM lines(tree('src')) WHERE text LIKE '%TODO%'");
+                crate::cprintln!("  SELECT status, COUNT(*) FROM csv('orders.csv') GROUP BY status");
+                crate::cprintln!("  WITH big AS (SELECT * FROM tree('.') WHERE size > 1e7) SELECT ext, COUNT(*) FROM big GROUP BY ext");
+                crate::cprintln!("  SELECT path FROM tree('.') WHERE name IN (SELECT name FROM tree('backup'))");
+                crate::cprintln!("  SELECT a.relative_path, a.size, b.size FROM tree('v1') a FULL JOIN tree('v2') b ON a.relative_path = b.relative_path WHERE a.size <> b.size OR a.name IS NULL OR b.name IS NULL");
+                crate::cprintln!("  SELECT path FROM tree('.') WHERE ext = 'log' AND size > 1e6 INTO STATE");
+                crate::cprintln!("  CREATE VIEW stale_logs AS SELECT path, size FROM tree('/var/log') WHERE modified < NOW() - INTERVAL '30 days'");
+                crate::cprintln!("  SELECT pid, name, cpu, rss FROM processes WHERE rss > 500000 ORDER BY cpu DESC");
+                crate::cprintln!("  EXPLAIN SELECT relative_path, hash FROM tree('.') WHERE relative_path LIKE 'src/%' AND depth <= 3");
+                crate::cprintln!("  SELECT hash, COUNT(*), SUM(size) FROM tree('.') WHERE hash IS NOT NULL GROUP BY hash HAVING COUNT(*) > 1");
+                crate::cprintln!("  SELECT name FROM files WHERE NOT is_dir AND ext IS NULL");
+                crate::cprintln!("  SELECT DATE(modified) AS day, COUNT(*) FROM tree('.') WHERE modified > NOW() - INTERVAL '7 days' GROUP BY day");
+                crate::cprintln!("  SELECT path, perms, owner FROM tree('.') WHERE perms LIKE '%w_' AND NOT is_symlink");
+                crate::cprintln!("  DELETE FROM files WHERE ext = 'tmp' AND modified < NOW() - INTERVAL '30 days'");
+                crate::cprintln!("  UPDATE files SET name = LOWER(name) WHERE ext = 'JPG'");
+                crate::cprintln!("  INSERT INTO files (name, is_dir) VALUES ('notes.md', false), ('build', true)");
+                crate::cprintln!("  INSERT INTO new_project (relative_path, is_dir) SELECT relative_path, is_dir FROM tree('template')");
+                crate::cprintln!("  DRY RUN UPDATE files SET parent = 'archive/', mode = '644' WHERE modified < '2026-01-01'");
+            }
             "PIPE" => {
                 crate::cprintln!("{}", "Command: PIPE".yellow().bold());
                 crate::cprintln!("Description: Creates a native execution pipeline to pass output from one command directly into another, optionally writing the final output to a file.");
@@ -212,6 +285,7 @@ pub fn show_docs(command_name: Option<String>) -> Result<String, String> {
         crate::cprintln!("\n{} Built-in Categories", "[*]".bright_green());
         crate::cprintln!("  {} CD, UP, WD, LD, DD, MKDIR, RMDIR, TOUCH, RM", "Directory:".cyan());
         crate::cprintln!("  {} S", "Search:".cyan());
+        crate::cprintln!("  {} INDEX ADD, INDEX REMOVE, INDEX REBUILD, INDEX STATUS", "Index:".bright_yellow());
         crate::cprintln!("  {} FAV ADD, FAV LS, FAV RM, RF", "Favorites:".green());
         crate::cprintln!("  {} SELECT, SV, LS, DS, RS", "State:".yellow());
         crate::cprintln!("  {} PIPE", "Pipelining:".yellow());
@@ -11,6 +11,8 @@ pub struct FileSystemState {
     current_path: PathBuf,
     pub aliases: HashMap<String, String>,
     pub interactive_commands: Vec<String>,
+    /// Lines entered at the shell prompt this session, as kept by its line editor.
+    pub history: Vec<String>,
 }
 
 impl FileSystemState {
@@ -28,6 +30,7 @@ impl FileSystemState {
             current_path,
             aliases: HashMap::new(),
             interactive_commands: default_interactive,
+            history: Vec::new(),
         };
 
         // Index the current directory immediately
@@ -35,6 +35,25 @@ pub fn resolve_path(relative_path: &Path, base_directory: &Path) -> PathBuf {
     }
 }
 
+// Drop "." and fold ".." without touching the filesystem, so /a/./b/../c becomes /a/c
+pub fn normalize_path(path: &Path) -> PathBuf {
+    use std::path::Component;
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            // ".." at the root stays at the root; a leading ".." of a relative path is kept
+            Component::ParentDir => {
+                if !normalized.pop() && !normalized.has_root() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
 // Handle special cases like double "." (parent directory) and "." (current directory)
 // fn normalize_path(path: &Path) -> Result<PathBuf, io::Error> {
 //     // canonicalize() resolves "..", ".", symbolic links, and normalizes the path
@@ -55,15 +74,15 @@ pub fn is_executable(path: &PathBuf) -> bool {
         return false;
     }
 
-    {
-        // On Windows, check file extension
-        if let Some(extension) = path.extension() {
-            let ext = extension.to_string_lossy().to_lowercase();
-            matches!(ext.as_str(), "exe" | "bat" | "cmd" | "com" | "scr" | "msi")
-        } else {
-            false
-        }
-    }
+    has_executable_extension(path)
+}
+
+/// Whether the file name ends in an extension Windows runs, such as `.exe` or `.bat`.
+pub fn has_executable_extension(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        let ext = extension.to_string_lossy().to_lowercase();
+        matches!(ext.as_str(), "exe" | "bat" | "cmd" | "com" | "scr" | "msi")
+    })
 }
 
 pub fn create_dir(path: &Path) -> bool {
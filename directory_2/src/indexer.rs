@@ -1,4 +1,17 @@
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Bound;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::walker::{self, WalkOptions};
 use crate::{file_system_state::FileSystemState, filesystem};
 
 pub fn index_current_directory(file_system_state: &mut FileSystemState) {
@@ -15,3 +28,493 @@ pub fn index_current_directory(file_system_state: &mut FileSystemState) {
     }
     file_system_state.set_index(index);
 }
+
+// ---------------------PERSISTENT INDEX------------------------
+//
+// `INDEX ADD <dir>` keeps the metadata of everything below a directory in the
+// dir2 config directory (.directory_2/index, next to favorites), so SQL queries
+// over it read rows from there instead of walking the tree. A query first works
+// out from the index what its walk would return, given its depth limit and the
+// path prefixes of its WHERE clause. Of the directories that listing reads it
+// re-reads only those whose mtime changed, which picks up added, removed and
+// renamed entries, and re-stats each entry it returns so files changed in place
+// never show stale sizes, times or hashes. DELETE and UPDATE
+// do not use it at all. `INDEX REBUILD` stores the changes and re-hashes.
+
+/// The metadata SQL queries read from one entry, so its row can be built
+/// without touching the file.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct IndexedEntry {
+    /// Like `Path::is_dir`, true for symlinks to directories.
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    pub link_target: Option<String>,
+    pub size: u64,
+    /// Timestamps are nanoseconds since the Unix epoch.
+    pub modified: Option<i64>,
+    pub accessed: Option<i64>,
+    pub created: Option<i64>,
+    pub changed: i64,
+    /// The raw `st_mode`, file type bits included.
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub inode: u64,
+    pub nlink: u64,
+    /// SHA-256 of the contents, for roots indexed WITH HASH.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+impl IndexedEntry {
+    /// `metadata` is the entry's own, not its symlink target's.
+    pub fn new(path: &Path, metadata: &fs::Metadata, is_symlink: bool) -> IndexedEntry {
+        IndexedEntry {
+            is_dir: path.is_dir(),
+            is_symlink,
+            link_target: if is_symlink { fs::read_link(path).ok().map(|t| t.to_string_lossy().to_string()) } else { None },
+            size: metadata.len(),
+            modified: nanos(metadata.modified()),
+            accessed: nanos(metadata.accessed()),
+            created: nanos(metadata.created()),
+            changed: metadata.ctime() * 1_000_000_000 + metadata.ctime_nsec(),
+            mode: metadata.mode(),
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            inode: metadata.ino(),
+            nlink: metadata.nlink(),
+            sha256: None,
+        }
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.mode & libc::S_IFMT == libc::S_IFREG
+    }
+
+    /// A directory the walker descends into.
+    pub fn is_walked_dir(&self) -> bool {
+        self.is_dir && !self.is_symlink
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RootIndex {
+    /// Canonical path of the indexed directory.
+    root: PathBuf,
+    /// Whether file contents are hashed (`INDEX ADD <dir> WITH HASH`).
+    hashes: bool,
+    /// Last full update (ADD or REBUILD), in seconds since the Unix epoch.
+    updated: i64,
+    /// Keyed by path relative to the root, "" being the root itself.
+    entries: BTreeMap<String, IndexedEntry>,
+}
+
+/// One row of `INDEX STATUS`.
+pub struct IndexStatus {
+    pub root: PathBuf,
+    pub entries: usize,
+    pub hashed: usize,
+    pub updated: i64,
+    pub bytes: u64,
+}
+
+/// How an update changed an index, for the messages of ADD and REBUILD.
+#[derive(Default)]
+struct Changes {
+    added: usize,
+    changed: usize,
+    removed: usize,
+}
+
+impl RootIndex {
+    /// Reads `rel` (the root itself when empty) and what is below it, down to
+    /// `max_depth`. Hashes are kept for files whose inode, size and mtime match.
+    fn scan(&self, rel: &str, max_depth: Option<usize>) -> Result<BTreeMap<String, IndexedEntry>, String> {
+        let options = WalkOptions { min_depth: 0, max_depth, follow_links: false };
+        // Stat-ing and hashing happen on the walker's threads.
+        let entries = walker::walk(&self.root.join(rel), &options, |_| true, |entry| {
+            let metadata = entry.metadata().ok()?;
+            let key = entry.path().strip_prefix(&self.root).unwrap_or(entry.path()).to_string_lossy().to_string();
+            let mut indexed = IndexedEntry::new(entry.path(), &metadata, entry.path_is_symlink());
+            if self.hashes && indexed.is_file() {
+                indexed.sha256 = match self.entries.get(&key) {
+                    Some(old) if old.inode == indexed.inode && old.size == indexed.size && old.modified == indexed.modified => {
+                        old.sha256.clone()
+                    }
+                    _ => hash_file(entry.path()),
+                };
+            }
+            Some((key, indexed))
+        })?;
+        Ok(entries.into_iter().collect())
+    }
+
+    /// Re-reads the whole tree.
+    fn update(&mut self) -> Result<Changes, String> {
+        let fresh = self.scan("", None)?;
+        let mut changes = Changes::default();
+        for (key, entry) in &fresh {
+            match self.entries.get(key) {
+                None => changes.added += 1,
+                Some(old) if old.modified != entry.modified || old.size != entry.size || old.inode != entry.inode => {
+                    changes.changed += 1
+                }
+                Some(_) => {}
+            }
+        }
+        changes.removed = self.entries.keys().filter(|key| !fresh.contains_key(*key)).count();
+        self.entries = fresh;
+        self.updated = unix_now();
+        Ok(changes)
+    }
+
+    /// The keys below `prefix` that a walk of it down to `max_depth` returns, in
+    /// order. `filter` sees each key relative to `prefix` and works like the
+    /// walker's: an entry it rejects is neither listed nor descended into.
+    fn listing(&self, prefix: &str, max_depth: Option<usize>, filter: &dyn Fn(&str, &IndexedEntry) -> bool) -> Vec<String> {
+        if max_depth == Some(0) {
+            return Vec::new();
+        }
+        // Everything below a directory "dir" sorts from "dir/" up to "dir0", after
+        // the directory itself, so a skipped subtree is jumped over in one lookup.
+        let past = |dir: &str| Bound::Included(format!("{}0", dir));
+        let (mut from, end) = match prefix {
+            "" => (Bound::Excluded(String::new()), Bound::Unbounded),
+            _ => (Bound::Included(format!("{}/", prefix)), Bound::Excluded(format!("{}0", prefix))),
+        };
+        let mut skipped: HashSet<&str> = HashSet::new();
+        let mut keys = Vec::new();
+        while let Some((key, entry)) = self.entries.range((from.clone(), end.clone())).next() {
+            let relative = relative(prefix, key);
+            let mut ancestors = relative.match_indices('/').map(|(at, _)| &key[..key.len() - relative.len() + at]);
+            // The ancestor at the depth limit, whose contents are too deep.
+            let limit = max_depth.and_then(|max| ancestors.clone().nth(max - 1));
+            if let Some(dir) = limit.or_else(|| ancestors.find(|dir| skipped.contains(dir))) {
+                from = past(dir);
+                continue;
+            }
+            from = Bound::Excluded(key.clone());
+            if filter(relative, entry) {
+                keys.push(key.clone());
+            } else {
+                skipped.insert(key);
+            }
+        }
+        keys
+    }
+
+    /// Re-reads those of `dirs` whose mtime changed, i.e. that had entries added,
+    /// removed or renamed. Returns whether anything changed.
+    fn refresh(&mut self, dirs: &[String]) -> Result<bool, String> {
+        let mut changed = false;
+        for dir in dirs {
+            // Already dropped along with a parent that went away.
+            let Some(modified) = self.entries.get(dir).map(|entry| entry.modified) else {
+                continue;
+            };
+            let current = fs::symlink_metadata(self.root.join(dir));
+            match current {
+                Ok(metadata) if metadata.is_dir() => {
+                    if nanos(metadata.modified()) == modified {
+                        continue;
+                    }
+                }
+                _ => {
+                    self.remove_subtree(dir);
+                    changed = true;
+                    continue;
+                }
+            }
+            changed = true;
+            let fresh = self.scan(dir, Some(1))?;
+            let gone: Vec<String> = self
+                .subtree(dir)
+                .map(|(key, _)| key.clone())
+                .filter(|key| is_child(dir, key) && !fresh.contains_key(key))
+                .collect();
+            for key in gone {
+                self.remove_subtree(&key);
+            }
+            for (key, entry) in fresh {
+                let known = self.entries.get(&key).is_some_and(|known| known.is_walked_dir());
+                // A subdirectory keeps its stored mtime until it is re-read itself,
+                // which a shallower query may not get to.
+                if key != *dir && entry.is_walked_dir() && known {
+                    continue;
+                }
+                let new_dir = key != *dir && entry.is_walked_dir();
+                self.entries.insert(key.clone(), entry);
+                if new_dir {
+                    let below = self.scan(&key, None)?;
+                    self.entries.extend(below);
+                }
+            }
+        }
+        Ok(changed)
+    }
+
+    /// `rel` and everything below it.
+    fn subtree<'a>(&'a self, rel: &'a str) -> impl Iterator<Item = (&'a String, &'a IndexedEntry)> + 'a {
+        self.entries
+            .range(rel.to_string()..)
+            .take_while(move |(key, _)| key.starts_with(rel))
+            .filter(move |(key, _)| rel.is_empty() || key.len() == rel.len() || key.as_bytes()[rel.len()] == b'/')
+    }
+
+    fn remove_subtree(&mut self, rel: &str) {
+        let keys: Vec<String> = self.subtree(rel).map(|(key, _)| key.clone()).collect();
+        for key in keys {
+            self.entries.remove(&key);
+        }
+    }
+
+    fn file(&self) -> PathBuf {
+        index_file(&self.root)
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let file = self.file();
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+        }
+        let json = serde_json::to_string(self).map_err(|e| format!("Failed to serialize index: {}", e))?;
+        fs::write(&file, json).map_err(|e| format!("Failed to write {}: {}", file.display(), e))
+    }
+}
+
+/// `key`, which lies below `prefix`, relative to it.
+fn relative<'a>(prefix: &str, key: &'a str) -> &'a str {
+    if prefix.is_empty() { key } else { &key[prefix.len() + 1..] }
+}
+
+fn is_child(dir: &str, key: &str) -> bool {
+    match dir {
+        "" => !key.is_empty() && !key.contains('/'),
+        _ => key.strip_prefix(dir).and_then(|rest| rest.strip_prefix('/')).is_some_and(|name| !name.contains('/')),
+    }
+}
+
+fn nanos(time: std::io::Result<SystemTime>) -> Option<i64> {
+    time.ok().map(|t| match t.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_nanos() as i64,
+        Err(e) => -(e.duration().as_nanos() as i64),
+    })
+}
+
+fn hash_file(path: &Path) -> Option<String> {
+    let mut file = fs::File::open(path).ok()?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).ok()?;
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Where the index files live: index/ in the config directory, one JSON file per root.
+fn index_dir() -> PathBuf {
+    crate::utils::config_dir().join("index")
+}
+
+/// Where the index of `root` is saved.
+fn index_file(root: &Path) -> PathBuf {
+    let name = blake3::hash(root.as_os_str().as_bytes()).to_hex();
+    index_dir().join(format!("{}.json", &name[..16]))
+}
+
+lazy_static! {
+    /// The indexes on disk, loaded on first use and keyed by root.
+    static ref INDEXES: Mutex<Option<HashMap<PathBuf, RootIndex>>> = Mutex::new(None);
+}
+
+fn with_indexes<T>(f: impl FnOnce(&mut HashMap<PathBuf, RootIndex>) -> T) -> Result<T, String> {
+    let mut guard = INDEXES.lock().map_err(|_| String::from("The index is unavailable"))?;
+    let indexes = guard.get_or_insert_with(|| {
+        let mut indexes = HashMap::new();
+        let files = fs::read_dir(index_dir()).ok();
+        for file in files.into_iter().flatten().flatten() {
+            // A file that no longer parses is rebuilt by the next INDEX ADD.
+            let index = fs::read_to_string(file.path()).ok().and_then(|json| serde_json::from_str::<RootIndex>(&json).ok());
+            if let Some(index) = index {
+                indexes.insert(index.root.clone(), index);
+            }
+        }
+        indexes
+    });
+    Ok(f(indexes))
+}
+
+fn canonical_dir(path: &Path) -> Result<PathBuf, String> {
+    let root = fs::canonicalize(path).map_err(|e| format!("Cannot index '{}': {}", path.display(), e))?;
+    if !root.is_dir() {
+        return Err(format!("'{}' is not a directory", path.display()));
+    }
+    Ok(root)
+}
+
+/// `INDEX ADD <dir> [WITH HASH]`
+pub fn add_root(path: &Path, hashes: bool) -> Result<String, String> {
+    let root = canonical_dir(path)?;
+    with_indexes(|indexes| {
+        if let Some(covering) = indexes.keys().find(|r| root.starts_with(r)) {
+            return Err(format!("'{}' is already covered by the index of {}; use INDEX REBUILD to update it", root.display(), covering.display()));
+        }
+        let started = Instant::now();
+        let mut index = RootIndex { root: root.clone(), hashes, updated: 0, entries: BTreeMap::new() };
+        index.update()?;
+        index.save()?;
+        // A new root takes over the roots below it.
+        let nested: Vec<PathBuf> = indexes.keys().filter(|r| r.starts_with(&root)).cloned().collect();
+        for old in nested {
+            if let Some(old) = indexes.remove(&old) {
+                let _ = fs::remove_file(old.file());
+            }
+        }
+        let message = format!(
+            "Indexed {} entries under {} in {:.1}s",
+            index.entries.len().saturating_sub(1),
+            root.display(),
+            started.elapsed().as_secs_f64()
+        );
+        indexes.insert(root, index);
+        Ok(message)
+    })?
+}
+
+/// `INDEX REMOVE <dir>`
+pub fn remove_root(path: &Path) -> Result<String, String> {
+    let root = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    with_indexes(|indexes| {
+        let index = indexes.remove(&root).ok_or_else(|| format!("'{}' is not indexed. See INDEX STATUS", root.display()))?;
+        let file = index.file();
+        fs::remove_file(&file).map_err(|e| format!("Failed to remove {}: {}", file.display(), e))?;
+        Ok(format!("Removed the index of {}", root.display()))
+    })?
+}
+
+/// `INDEX REBUILD [<dir>]`: re-reads every indexed root, or the one given,
+/// re-hashing only files whose mtime or size changed.
+pub fn rebuild(path: Option<&Path>) -> Result<String, String> {
+    let root = path.map(|p| fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf()));
+    with_indexes(|indexes| {
+        let mut messages = Vec::new();
+        for index in indexes.values_mut().filter(|index| root.as_ref().is_none_or(|r| *r == index.root)) {
+            let changes = index.update()?;
+            index.save()?;
+            messages.push(format!(
+                "{}: {} added, {} changed, {} removed",
+                index.root.display(),
+                changes.added,
+                changes.changed,
+                changes.removed
+            ));
+        }
+        match (messages.is_empty(), &root) {
+            (true, Some(root)) => Err(format!("'{}' is not indexed. See INDEX STATUS", root.display())),
+            (true, None) => Ok(String::from("Nothing is indexed. Use INDEX ADD <dir> to index a directory")),
+            (false, _) => Ok(messages.join("\n")),
+        }
+    })?
+}
+
+/// `INDEX STATUS`
+pub fn status() -> Result<Vec<IndexStatus>, String> {
+    with_indexes(|indexes| {
+        let mut status: Vec<IndexStatus> = indexes
+            .values()
+            .map(|index| IndexStatus {
+                root: index.root.clone(),
+                entries: index.entries.len().saturating_sub(1),
+                hashed: index.entries.values().filter(|e| e.sha256.is_some()).count(),
+                updated: index.updated,
+                bytes: fs::metadata(index.file()).ok().map_or(0, |m| m.len()),
+            })
+            .collect();
+        status.sort_by(|a, b| a.root.cmp(&b.root));
+        status
+    })
+}
+
+/// The indexed root that covers `path`, if any.
+pub fn covering_root(path: &Path) -> Option<PathBuf> {
+    let path = fs::canonicalize(path).ok()?;
+    with_indexes(|indexes| indexes.keys().filter(|root| path.starts_with(root)).max().cloned()).ok()?
+}
+
+/// What a walk of `path` down to `max_depth` would return, when `path` lies
+/// inside an indexed root, keyed by path relative to `path`. `filter` gets each
+/// entry's full path and, as with `walker::walk`, an entry it rejects is neither
+/// returned nor descended into. Only the directories the listing reads are
+/// re-read, and only when they changed since the last look; then each listed
+/// entry is re-stat-ed: one whose size, times or inode moved on (a file edited
+/// in place leaves its directory alone) gets fresh metadata and loses its stored
+/// hash. What the index still saves is the directory reads and the hashing.
+pub fn indexed_entries(
+    path: &Path,
+    max_depth: Option<usize>,
+    filter: impl Fn(&Path, &IndexedEntry) -> bool,
+) -> Option<Vec<(String, IndexedEntry)>> {
+    let path = fs::canonicalize(path).ok()?;
+    // Spares loading the indexes for a directory none of them covers.
+    if !path.ancestors().any(|dir| index_file(dir).exists()) {
+        return None;
+    }
+    let filter = |relative: &str, entry: &IndexedEntry| filter(&path.join(relative), entry);
+    let entries = with_indexes(|indexes| {
+        let index = indexes.values_mut().filter(|index| path.starts_with(&index.root)).max_by(|a, b| a.root.cmp(&b.root))?;
+        let prefix = path.strip_prefix(&index.root).ok()?.to_string_lossy().to_string();
+        // The directories whose contents are listed: `path` and the listed ones above the depth limit.
+        let mut dirs = vec![prefix.clone()];
+        dirs.extend(
+            index
+                .listing(&prefix, max_depth, &filter)
+                .into_iter()
+                .filter(|key| index.entries[key].is_walked_dir() && max_depth.is_none_or(|max| relative(&prefix, key).split('/').count() < max)),
+        );
+        // When cancelled, the query falls back to walking and reports it.
+        if index.refresh(&dirs).ok()? {
+            let _ = index.save();
+        }
+        let entries = index
+            .listing(&prefix, max_depth, &filter)
+            .into_iter()
+            .map(|key| (relative(&prefix, &key).to_string(), index.entries[&key].clone()))
+            .collect();
+        Some(entries)
+    })
+    .ok()??;
+    let entries = recheck(&path, entries);
+    (!walker::interrupted()).then_some(entries)
+}
+
+/// Re-stats entries on `walker::threads()` threads; ones that vanished are dropped.
+fn recheck(path: &Path, entries: Vec<(String, IndexedEntry)>) -> Vec<(String, IndexedEntry)> {
+    let chunk = entries.len().div_ceil(walker::threads()).max(1);
+    let check = |entries: &[(String, IndexedEntry)]| -> Vec<(String, IndexedEntry)> {
+        let mut fresh = Vec::with_capacity(entries.len());
+        for (relative, entry) in entries {
+            if walker::interrupted() {
+                break;
+            }
+            let full = path.join(relative);
+            let Ok(metadata) = fs::symlink_metadata(&full) else {
+                continue;
+            };
+            let unchanged = metadata.len() == entry.size
+                && metadata.ino() == entry.inode
+                && nanos(metadata.modified()) == entry.modified
+                && metadata.ctime() * 1_000_000_000 + metadata.ctime_nsec() == entry.changed;
+            let entry = if unchanged { entry.clone() } else { IndexedEntry::new(&full, &metadata, metadata.file_type().is_symlink()) };
+            fresh.push((relative.clone(), entry));
+        }
+        fresh
+    };
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = entries.chunks(chunk).map(|part| scope.spawn(move || check(part))).collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+            .collect()
+    })
+}
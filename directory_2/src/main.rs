@@ -1,5 +1,6 @@
 mod commands;
 mod completion;
+mod data_files;
 mod delegation;
 mod favorites;
 mod file_system_state;
@@ -9,6 +10,9 @@ mod indexer;
 mod parser;
 mod search;
 mod sql_engine;
+mod system_tables;
+mod views;
+mod walker;
 #[macro_use]
 pub mod utils;
 pub mod shell;
@@ -137,6 +137,19 @@ pub enum Command {
     RunFav {
         index: usize,
     },
+
+    // Index Commands
+    IndexAdd {
+        directory: String,
+        hashes: bool,
+    },
+    IndexRemove {
+        directory: String,
+    },
+    IndexRebuild {
+        directory: Option<String>,
+    },
+    IndexStatus,
 }
 
 pub fn parse_command(input: &str) -> Result<Command, String> {
@@ -155,7 +168,7 @@ pub fn parse_command(input: &str) -> Result<Command, String> {
         }
         "SELECT" => {
             if tokens.iter().any(|t| t.to_uppercase() == "FROM") {
-                if tokens.len() == 4 && tokens[2].to_uppercase() == "FROM" {
+                if tokens.len() == 4 && tokens[2].to_uppercase() == "FROM" && !is_sql_table(&tokens[3]) {
                     return Ok(Command::Select {
                         target: tokens[1].clone(),
                         from: tokens[3].clone(),
@@ -188,7 +201,18 @@ pub fn parse_command(input: &str) -> Result<Command, String> {
                 return Err("Syntax Error: FILTER <column> <operator> <value>".to_string());
             }
         }
-        "UPDATE" | "DELETE" | "INSERT" => {
+        "UPDATE" | "DELETE" | "INSERT" | "EXPLAIN" | "WITH" | "CREATE" => {
+            return Ok(Command::SqlQuery {
+                query: input.to_string(),
+            });
+        }
+        "DRY" if tokens.len() > 2 && tokens[1].eq_ignore_ascii_case("RUN") => {
+            return Ok(Command::SqlQuery {
+                query: input.to_string(),
+            });
+        }
+        // DROP STATE stays a shell command.
+        "DROP" if tokens.len() > 2 && tokens[1].eq_ignore_ascii_case("VIEW") => {
             return Ok(Command::SqlQuery {
                 query: input.to_string(),
             });
@@ -334,11 +358,25 @@ pub fn parse_command(input: &str) -> Result<Command, String> {
         // Favorite Commands
         "RF" => parse_run(&tokens),
         "FAV" => parse_fav(&tokens),
+        // Index Commands
+        "INDEX" => parse_index(&tokens),
         // Search Commands
         _ => parse_unknown(&tokens),
     };
 }
 
+// Sources that only the SQL engine understands; `SELECT x FROM <dir>` stays a STATE command otherwise.
+fn is_sql_table(source: &str) -> bool {
+    source.eq_ignore_ascii_case("files")
+        || crate::system_tables::SYSTEM_TABLES.iter().any(|t| source.eq_ignore_ascii_case(t))
+        || source.contains('(')
+        || source.starts_with('\'')
+        // Paths: ~/projects, /abs, ./dir, ../dir, $VAR or sub/dir.
+        || source.starts_with(['~', '/', '.', '$'])
+        || source.contains('/')
+        || crate::views::exists(source)
+}
+
 fn tokenize(input: &str) -> Result<Vec<String>, String> {
     let mut tokens = Vec::new();
     let mut current_token = String::new();
@@ -596,6 +634,24 @@ fn parse_fav(tokens: &[String]) -> Result<Command, String> {
     }
 }
 
+fn parse_index(tokens: &[String]) -> Result<Command, String> {
+    let usage = || "Expected INDEX ADD <dir> [WITH HASH], INDEX REMOVE <dir>, INDEX REBUILD [<dir>] or INDEX STATUS".red().to_string();
+    if tokens.len() < 2 {
+        return Err(usage());
+    }
+    match (tokens[1].to_uppercase().as_str(), &tokens[2..]) {
+        ("ADD", [directory]) => Ok(Command::IndexAdd { directory: directory.clone(), hashes: false }),
+        ("ADD", [directory, with, hash]) if with.eq_ignore_ascii_case("WITH") && hash.eq_ignore_ascii_case("HASH") => {
+            Ok(Command::IndexAdd { directory: directory.clone(), hashes: true })
+        }
+        ("REMOVE" | "RM", [directory]) => Ok(Command::IndexRemove { directory: directory.clone() }),
+        ("REBUILD", []) => Ok(Command::IndexRebuild { directory: None }),
+        ("REBUILD", [directory]) => Ok(Command::IndexRebuild { directory: Some(directory.clone()) }),
+        ("STATUS", []) => Ok(Command::IndexStatus),
+        _ => Err(usage()),
+    }
+}
+
 fn parse_run(tokens: &[String]) -> Result<Command, String> {
     // Pattern Matching based on Length of Tokens and First Token
     match (tokens.len(), tokens[0].to_uppercase().as_str()) {
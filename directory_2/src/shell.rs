@@ -38,7 +38,9 @@ pub async fn run_shell(
                     continue;
                 }
                 
-                rl.add_history_entry(line)?;
+                if rl.add_history_entry(line)? {
+                    sys_state.history.push(line.to_string());
+                }
 
                 if line.to_uppercase() == "EXIT" || line.to_uppercase() == "QUIT" {
                     break;
@@ -1,162 +1,2901 @@
+use crate::favorites::FavoritesManager;
 use crate::file_system_state::FileSystemState;
+use crate::indexer::IndexedEntry;
+use crate::walker::{self, Entry, WalkOptions};
+use crate::value::Value as CellValue;
+use crate::views::View;
 use sqlparser::dialect::GenericDialect;
 use sqlparser::parser::Parser;
-use sqlparser::ast::{Statement, Query, SetExpr, TableFactor, SelectItem, Expr, BinaryOperator, Value};
-use comfy_table::{Table, Cell, Color as CColor, Attribute};
-use comfy_table::presets::UTF8_FULL;
-use std::fs;
-use std::os::unix::fs::MetadataExt;
-use chrono::{DateTime, Local};
-
-pub fn execute_sql_query(sys_state: &mut FileSystemState, query: &str) -> Result<String, String> {
+use sqlparser::tokenizer::{Token, Tokenizer};
+use sqlparser::ast::{
+    Statement, Query, Select, SetExpr, TableFactor, SelectItem, Expr, BinaryOperator, UnaryOperator, Value, OrderBy,
+    OrderByKind, LimitClause, GroupByExpr, Function, FunctionArguments, FunctionArg, FunctionArgExpr,
+    DuplicateTreatment, Delete, FromTable, Update, AssignmentTarget, Insert, TableObject, ObjectName,
+    TableFunctionArgs, TableWithJoins, JoinOperator, JoinConstraint, SelectItemQualifiedWildcardKind, Distinct,
+    Ident, CreateView, ObjectType, DataType, TimezoneInfo, TypedString, Interval,
+};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeDelta, TimeZone};
+use lazy_static::lazy_static;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::sync::Mutex;
+
+pub fn execute_sql_query(sys_state: &mut FileSystemState, favorites: &FavoritesManager, query: &str) -> Result<CellValue, String> {
+    // Ctrl-C cancels the query rather than killing the shell.
+    let _interrupts = walker::catch_interrupts();
+    clear_pattern_cache();
     let dialect = GenericDialect {};
-    let ast = Parser::parse_sql(&dialect, query).map_err(|e| format!("SQL Parse Error: {}", e))?;
+    let (query, options) = strip_modifiers(query);
+    let query = rewrite_query(&query);
+    let ast = Parser::parse_sql(&dialect, &query).map_err(|e| format!("SQL Parse Error: {}", e))?;
 
     if ast.is_empty() {
         return Err("Empty SQL query".to_string());
     }
 
-    match &ast[0] {
-        Statement::Query(q) => execute_select(sys_state, q),
-        _ => Err("Unsupported SQL statement. Only SELECT is supported currently.".to_string()),
+    let (statement, dry_run, explain) = match &ast[0] {
+        Statement::Explain { statement, .. } => (&**statement, true, true),
+        statement => (statement, options.dry_run, false),
+    };
+    if options.cascade && !matches!(statement, Statement::Delete(_)) {
+        return Err("CASCADE is only valid at the end of a DELETE".to_string());
+    }
+    if let Some(into) = options.into_state {
+        return match statement {
+            Statement::Query(q) if !dry_run => select_into_state(sys_state, favorites, q, into),
+            _ => Err("INTO STATE is only valid at the end of a SELECT".to_string()),
+        };
+    }
+
+    match statement {
+        Statement::Query(q) if explain => explain_select(sys_state, favorites, q),
+        Statement::Query(q) if !dry_run => execute_select(sys_state, favorites, q),
+        Statement::Delete(delete) => execute_delete(sys_state, favorites, delete, dry_run, options.cascade),
+        Statement::Update(update) => execute_update(sys_state, favorites, update, dry_run),
+        Statement::Insert(insert) => execute_insert(sys_state, favorites, insert, dry_run),
+        Statement::CreateView(create) if !dry_run => create_view(create),
+        Statement::Drop { object_type: ObjectType::View, if_exists, names, .. } if !dry_run => drop_views(names, *if_exists),
+        Statement::Query(_) => Err("DRY RUN is only supported for DELETE, UPDATE and INSERT; use EXPLAIN SELECT to see how a query runs".to_string()),
+        Statement::CreateView(_) | Statement::Drop { object_type: ObjectType::View, .. } => {
+            Err("DRY RUN and EXPLAIN are not supported for CREATE VIEW and DROP VIEW".to_string())
+        }
+        _ => Err("Unsupported SQL statement. Only SELECT, DELETE, UPDATE, INSERT, CREATE VIEW and DROP VIEW are supported currently.".to_string()),
+    }
+}
+
+/// dir2-specific words around a statement that sqlparser does not know.
+struct Modifiers {
+    /// `DRY RUN <statement>`: report what would change without touching anything.
+    dry_run: bool,
+    /// `DELETE ... CASCADE`: allow deleting directories together with their contents.
+    cascade: bool,
+    /// `SELECT ... INTO STATE [APPEND]`: load the selected paths into STATE.
+    into_state: Option<IntoState>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum IntoState {
+    Replace,
+    Append,
+}
+
+fn strip_modifiers(query: &str) -> (String, Modifiers) {
+    let mut query = query.trim().trim_end_matches(';').trim_end();
+    let mut options = Modifiers { dry_run: false, cascade: false, into_state: None };
+
+    let mut words = query.split_whitespace();
+    if let (Some(first), Some(second)) = (words.next(), words.next())
+        && first.eq_ignore_ascii_case("DRY")
+        && second.eq_ignore_ascii_case("RUN")
+    {
+        options.dry_run = true;
+        query = query[first.len()..].trim_start()[second.len()..].trim_start();
+    }
+    if let Some(last) = query.split_whitespace().last()
+        && last.eq_ignore_ascii_case("CASCADE")
+    {
+        options.cascade = true;
+        query = query[..query.len() - last.len()].trim_end();
+    }
+    let words: Vec<&str> = query.split_whitespace().rev().take(3).collect();
+    let (mode, len) = match words.as_slice() {
+        [append, state, into, ..]
+            if append.eq_ignore_ascii_case("APPEND") && state.eq_ignore_ascii_case("STATE") && into.eq_ignore_ascii_case("INTO") =>
+        {
+            (Some(IntoState::Append), 3)
+        }
+        [state, into, ..] if state.eq_ignore_ascii_case("STATE") && into.eq_ignore_ascii_case("INTO") => {
+            (Some(IntoState::Replace), 2)
+        }
+        _ => (None, 0),
+    };
+    if let Some(mode) = mode {
+        options.into_state = Some(mode);
+        for word in &words[..len] {
+            query = query[..query.len() - word.len()].trim_end();
+        }
     }
+    (query.to_string(), options)
 }
 
-fn execute_select(sys_state: &mut FileSystemState, query: &Query) -> Result<String, String> {
-    if let SetExpr::Select(select) = &*query.body {
-        // Check FROM clause
-        if select.from.is_empty() {
-            return Err("Missing FROM clause".to_string());
+/// Rewrites the dir2 extensions sqlparser cannot read into syntax it can:
+/// paths such as `'/var/log'` or `~/projects` after FROM, JOIN or INTO become
+/// double-quoted identifiers, the `GLOB` operator becomes `OPERATOR(glob)`, and
+/// the `group` column is quoted wherever GROUP is not followed by BY.
+fn rewrite_query(query: &str) -> String {
+    let chars: Vec<char> = query.chars().collect();
+    let mut out = String::with_capacity(query.len());
+    let mut quote: Option<char> = None;
+    let mut prev_word = String::new();
+    let mut expect_source = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if let Some(q) = quote {
+            out.push(c);
+            if c == q {
+                quote = None;
+            }
+            i += 1;
+            continue;
+        }
+
+        if expect_source && !c.is_whitespace() {
+            expect_source = false;
+            let path = if c == '\'' {
+                let end = (i + 1..chars.len()).find(|&j| chars[j] == '\'').unwrap_or(chars.len());
+                let path: String = chars[i + 1..end].iter().collect();
+                Some((path, (end + 1).min(chars.len())))
+            } else if c == '"' {
+                // Already an identifier, e.g. `FROM "/tmp/t1/src"`.
+                None
+            } else {
+                let end = (i..chars.len())
+                    .find(|&j| chars[j].is_whitespace() || matches!(chars[j], ',' | '(' | ')' | ';'))
+                    .unwrap_or(chars.len());
+                let token: String = chars[i..end].iter().collect();
+                (matches!(c, '~' | '/' | '.' | '$') || token.contains('/')).then_some((token, end))
+            };
+            if let Some((path, end)) = path {
+                out.push('"');
+                out.push_str(&path.replace('"', "\"\""));
+                out.push('"');
+                i = end;
+                continue;
+            }
+        }
+
+        if c == '\'' || c == '"' {
+            quote = Some(c);
+        } else if c.is_alphabetic() || c == '_' {
+            let end = (i..chars.len())
+                .find(|&j| !(chars[j].is_alphanumeric() || chars[j] == '_'))
+                .unwrap_or(chars.len());
+            let word: String = chars[i..end].iter().collect();
+            i = end;
+            if word.eq_ignore_ascii_case("GLOB") {
+                out.push_str("OPERATOR(glob)");
+                prev_word = word;
+                continue;
+            }
+            if word.eq_ignore_ascii_case("GROUP") {
+                let next = chars[i..].iter().skip_while(|c| c.is_whitespace()).take(2).collect::<String>();
+                if !next.eq_ignore_ascii_case("BY") {
+                    out.push_str("\"group\"");
+                    prev_word = word;
+                    continue;
+                }
+            }
+            // `a IS DISTINCT FROM 'x'` compares against a string, not a path.
+            expect_source = (word.eq_ignore_ascii_case("FROM") && !prev_word.eq_ignore_ascii_case("DISTINCT"))
+                || word.eq_ignore_ascii_case("JOIN")
+                || word.eq_ignore_ascii_case("INTO");
+            out.push_str(&word);
+            prev_word = word;
+            continue;
         }
+        out.push(c);
+        i += 1;
+    }
+    out
+}
+
+/// Expands `~` and `$VAR` in a path written inside a query and resolves it
+/// against the current directory, dropping `.` and `..` so that `tree('.')`
+/// gives paths like `/tmp/t/sub/b.rs` that LIKE and prefix pruning can match.
+pub(crate) fn expand_path(raw: &str, current_path: &Path) -> PathBuf {
+    let expanded = crate::utils::substitute_env_vars(raw);
+    let expanded = match (expanded.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => home.join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(expanded),
+    };
+    crate::filesystem::normalize_path(&crate::filesystem::resolve_path(&expanded, current_path))
+}
+
+/// Columns exposed by the `files` table, in the order `SELECT *` returns them.
+const FILES_COLUMNS: &[&str] = &[
+    "name", "ext", "size", "modified", "is_dir", "path", "relative_path", "parent", "depth",
+    "mode", "perms", "uid", "gid", "owner", "group", "inode", "nlink", "accessed", "created", "changed",
+    "is_symlink", "link_target", "is_executable",
+];
+
+/// Columns that read file contents. They are computed only when an expression
+/// uses them, so `SELECT *` leaves them out. `hash` is the SHA-256 digest.
+const CONTENT_COLUMNS: &[&str] = &["hash", "sha256", "blake3"];
+
+fn is_column(name: &str) -> bool {
+    FILES_COLUMNS.contains(&name) || CONTENT_COLUMNS.contains(&name)
+}
 
-        let from = &select.from[0].relation;
-        let target_dir = match from {
-            TableFactor::Table { name, .. } => name.to_string(),
-            _ => return Err("Unsupported FROM clause".to_string()),
+/// A row of the `files` table, keyed by lowercase column name.
+pub(crate) type Row = HashMap<String, TypedValue>;
+
+/// An output row: the SELECT items in the order they were written.
+type Projected = Vec<(String, TypedValue)>;
+
+/// The results of the WITH queries a SELECT can read from, by lowercase name.
+type Ctes = HashMap<String, (Vec<String>, Vec<Row>)>;
+
+fn execute_select(sys_state: &mut FileSystemState, favorites: &FavoritesManager, query: &Query) -> Result<CellValue, String> {
+    let (columns, rows) = run_select(sys_state, favorites, &Ctes::new(), query)?;
+    let rows = rows
+        .into_iter()
+        .map(|projected| {
+            projected
+                .into_iter()
+                .map(|(col, val)| (col, val.to_cell()))
+                .collect::<HashMap<_, _>>()
+        })
+        .collect();
+
+    Ok(CellValue::Table(rows, columns))
+}
+
+/// `SELECT ... INTO STATE [APPEND]`: puts the selected paths into STATE so RUN
+/// STATE, META STATE and FAV SET STATE work on them. Paths come from the `path`
+/// column, or the only column selected; relative ones are taken from the current
+/// directory.
+fn select_into_state(sys_state: &mut FileSystemState, favorites: &FavoritesManager, query: &Query, into: IntoState) -> Result<CellValue, String> {
+    let (_, rows) = run_select(sys_state, favorites, &Ctes::new(), query)?;
+    let mut paths = match (into, sys_state.get_current_state()) {
+        (IntoState::Append, Some(state)) => state.clone(),
+        _ => Vec::new(),
+    };
+    let before = paths.len();
+    for row in rows {
+        let value = match row.as_slice() {
+            [(_, value)] => value,
+            columns => match columns.iter().find(|(col, _)| col == "path") {
+                Some((_, value)) => value,
+                None => return Err("INTO STATE needs a path column, e.g. SELECT path FROM ... INTO STATE".to_string()),
+            },
         };
+        let path = match value {
+            TypedValue::Text(p) => expand_path(p, sys_state.get_current_path()),
+            TypedValue::Null => continue,
+            other => return Err(format!("INTO STATE expects paths, got a {}", other.describe())),
+        };
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+
+    let added = paths.len() - before;
+    let total = paths.len();
+    sys_state.set_current_state(paths);
+    Ok(CellValue::String(match into {
+        IntoState::Replace => format!("Selected {} file(s) into STATE", total),
+        IntoState::Append => format!("Added {} file(s) to STATE ({} in total)", added, total),
+    }))
+}
+
+/// `CREATE [OR REPLACE] VIEW name [(columns)] AS SELECT ...`: saves the SELECT so
+/// that `FROM name` runs it again. Nothing is run until then, so a view can name
+/// a directory that only exists on some machines.
+fn create_view(create: &CreateView) -> Result<CellValue, String> {
+    let name = match create.name.0.as_slice() {
+        [part] => part.as_ident().map(|ident| ident.value.clone()),
+        _ => None,
+    }
+    .ok_or_else(|| format!("Unsupported view name '{}'", create.name))?;
+    let lower = name.to_lowercase();
+    if lower == "files" || crate::system_tables::SYSTEM_TABLES.contains(&lower.as_str()) {
+        return Err(format!("'{}' is a built-in table; choose another name for the view", name));
+    }
+    if create.materialized || create.temporary {
+        return Err("MATERIALIZED and TEMPORARY views are not supported".to_string());
+    }
+    if !matches!(&*create.query.body, SetExpr::Select(_)) {
+        return Err("A view must be a single SELECT".to_string());
+    }
+
+    let existed = crate::views::exists(&lower);
+    if existed && create.if_not_exists {
+        return Ok(CellValue::String(format!("View {} already exists", name)));
+    }
+    let view = View {
+        query: create.query.to_string(),
+        columns: create.columns.iter().map(|c| c.name.value.clone()).collect(),
+    };
+    if !crate::views::create(&lower, view, create.or_replace)? {
+        return Err(format!("View '{}' already exists; use CREATE OR REPLACE VIEW to change it", name));
+    }
+    Ok(CellValue::String(format!("{} view {}", if existed { "Replaced" } else { "Created" }, name)))
+}
+
+/// `DROP VIEW [IF EXISTS] name, ...`: forgets saved views. Nothing is dropped
+/// unless every view named exists (or IF EXISTS is given).
+fn drop_views(names: &[ObjectName], if_exists: bool) -> Result<CellValue, String> {
+    let mut dropped = Vec::new();
+    for name in names {
+        let name = match name.0.as_slice() {
+            [part] => part.as_ident().map(|ident| ident.value.clone()),
+            _ => None,
+        }
+        .ok_or_else(|| format!("Unsupported view name '{}'", name))?;
+        if crate::views::exists(&name) {
+            dropped.push(name);
+        } else if !if_exists {
+            return Err(format!("No view named '{}'", name));
+        }
+    }
+    for name in &dropped {
+        crate::views::remove(name)?;
+    }
+    Ok(CellValue::String(match dropped.as_slice() {
+        [] => "No views dropped".to_string(),
+        [name] => format!("Dropped view {}", name),
+        names => format!("Dropped views {}", names.join(", ")),
+    }))
+}
+
+/// `EXPLAIN SELECT ...`: describes how the query would run without walking any
+/// directory, reading any data file or system table, or running any subquery.
+fn explain_select(sys_state: &FileSystemState, favorites: &FavoritesManager, query: &Query) -> Result<CellValue, String> {
+    let mut plan = Vec::new();
+    explain_query(sys_state, favorites, &Ctes::new(), query, 0, &mut plan)?;
+    let expensive = content_columns_used(query);
+    if !expensive.is_empty() {
+        plan.push(format!(
+            "Reads and hashes file contents for: {} (cached by inode, size and modification time)",
+            expensive.join(", ")
+        ));
+    }
+    Ok(CellValue::String(plan.join("\n")))
+}
 
-        if target_dir != "." && target_dir != "files" {
-            return Err(format!("Unsupported table '{}'. Use '.' or 'files' to query the current directory.", target_dir));
-        }
-
-        let current_path = sys_state.get_current_path();
-        
-        let mut table = Table::new();
-        table.load_preset(UTF8_FULL);
-        table.set_header(vec![
-            Cell::new("Name").fg(CColor::Green).add_attribute(Attribute::Bold),
-            Cell::new("Ext").fg(CColor::Green).add_attribute(Attribute::Bold),
-            Cell::new("Size").fg(CColor::Green).add_attribute(Attribute::Bold),
-            Cell::new("Modified").fg(CColor::Green).add_attribute(Attribute::Bold),
-            Cell::new("Is Dir").fg(CColor::Green).add_attribute(Attribute::Bold),
-        ]);
-
-        // Basic traversal
-        for entry in walkdir::WalkDir::new(current_path).min_depth(1).max_depth(1) {
-            let entry = match entry {
-                Ok(e) => e,
-                Err(_) => continue,
+fn explain_query(
+    sys_state: &FileSystemState,
+    favorites: &FavoritesManager,
+    ctes: &Ctes,
+    query: &Query,
+    depth: usize,
+    plan: &mut Vec<String>,
+) -> Result<(), String> {
+    let pad = "  ".repeat(depth);
+    let mut local_ctes = ctes.clone();
+    if let Some(with) = &query.with {
+        for cte in &with.cte_tables {
+            plan.push(format!("{}WITH {} (runs once):", pad, cte.alias.name));
+            explain_query(sys_state, favorites, &local_ctes, &cte.query, depth + 1, plan)?;
+            let columns = match cte.alias.columns.as_slice() {
+                [] => select_labels(&cte.query),
+                renamed => renamed.iter().map(|c| c.name.value.clone()).collect(),
             };
+            local_ctes.insert(cte.alias.name.value.to_lowercase(), (columns, Vec::new()));
+        }
+    }
+    let SetExpr::Select(select) = &*query.body else {
+        return Err("Unsupported query type".to_string());
+    };
 
-            let path = entry.path();
-            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
-            let ext = path.extension().unwrap_or_default().to_string_lossy().to_string();
-            let metadata = entry.metadata().ok();
-            
-            let is_dir = path.is_dir();
-            
-            let size = if let Some(m) = &metadata {
-                m.len()
-            } else {
-                0
+    // Mirrors `resolve_from`: only a lone, unaliased table gets the WHERE pushed down.
+    let single = select.from.len() == 1
+        && select.from[0].joins.is_empty()
+        && matches!(&select.from[0].relation, TableFactor::Table { alias: None, .. });
+    let mut columns: Vec<String> = Vec::new();
+    for (i, table) in select.from.iter().enumerate() {
+        let mut relations = vec![(&table.relation, (i > 0).then(|| "CROSS JOIN".to_string()))];
+        for join in &table.joins {
+            let (kind, on) = join_kind(&join.join_operator)?;
+            let label = match kind {
+                JoinKind::Inner if on.is_none() => "CROSS JOIN",
+                JoinKind::Inner => "INNER JOIN",
+                JoinKind::Left => "LEFT JOIN",
+                JoinKind::Right => "RIGHT JOIN",
+                JoinKind::Full => "FULL JOIN",
+            };
+            relations.push((&join.relation, Some(label.to_string())));
+        }
+        for (n, (relation, label)) in relations.into_iter().enumerate() {
+            let on = match (n, label.is_some()) {
+                (0, _) | (_, false) => None,
+                _ => join_kind(&table.joins[n - 1].join_operator)?.1,
+            };
+            match &label {
+                Some(label) => plan.push(format!("{}{} {}", pad, label, relation)),
+                None => plan.push(format!("{}FROM {}", pad, relation)),
+            }
+            // Subqueries and views are explained rather than run, so their columns come from their SELECT list.
+            let view = match relation {
+                TableFactor::Table { name, args, .. } => view_named(name, args.as_ref(), &local_ctes)?,
+                _ => None,
+            };
+            let unread = match (relation, &view) {
+                (TableFactor::Table { name, args, .. }, None) => unread_table(sys_state, &local_ctes, name, args.as_ref())?,
+                _ => None,
+            };
+            let (source, names) = match (relation, &view) {
+                (_, None) if let Some((description, names)) = &unread => {
+                    plan.push(format!("{}  {}", pad, description));
+                    (None, names.clone())
+                }
+                (TableFactor::Derived { subquery, .. }, _) => (None, select_labels(subquery)),
+                (_, Some((_, view, query))) if view.columns.is_empty() => (None, select_labels(query)),
+                (_, Some((_, view, _))) => (None, view.columns.clone()),
+                _ => {
+                    let mut source = resolve_source(sys_state, favorites, &local_ctes, relation)?;
+                    if single {
+                        source.push_down(select.selection.as_ref());
+                    }
+                    let names = source.columns();
+                    (Some(source), names)
+                }
+            };
+            let qualified = match table_name(relation) {
+                Ok(name) => qualify_columns(&name, names),
+                Err(_) => Vec::new(),
+            };
+            if let Some(on) = &on {
+                let strategy = match equi_join_columns(on, &columns, &qualified) {
+                    Some((l, r)) => format!("matches rows by key {} = {}", l, r),
+                    None => "compares every pair of rows".to_string(),
+                };
+                plan.push(format!("{}  ON {}: {}", pad, on, strategy));
+            }
+            columns.extend(qualified);
+            match (source, relation) {
+                (Some(source), _) => explain_source(&source, depth + 1, plan),
+                (None, TableFactor::Derived { subquery, .. }) => {
+                    explain_query(sys_state, favorites, &local_ctes, subquery, depth + 1, plan)?
+                }
+                (None, _) => {
+                    if let Some((name, _, query)) = &view {
+                        plan.push(format!("{}  Run view {} (saved query):", pad, name));
+                        enter_view(name, || explain_query(sys_state, favorites, &Ctes::new(), query, depth + 2, plan))?;
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(selection) = &select.selection {
+        plan.push(format!("{}WHERE {} (checked for every row)", pad, selection));
+        explain_subqueries(sys_state, favorites, &local_ctes, "WHERE", [selection], depth, plan)?;
+    }
+    if is_aggregate_query(select) {
+        match &select.group_by {
+            GroupByExpr::Expressions(exprs, _) if !exprs.is_empty() => {
+                let keys: Vec<String> = exprs.iter().map(|e| e.to_string()).collect();
+                plan.push(format!("{}GROUP BY {}", pad, keys.join(", ")));
+            }
+            _ => plan.push(format!("{}Aggregate all rows into one", pad)),
+        }
+        if let Some(having) = &select.having {
+            plan.push(format!("{}HAVING {}", pad, having));
+            explain_subqueries(sys_state, favorites, &local_ctes, "HAVING", [having], depth, plan)?;
+        }
+    }
+    let projected = select.projection.iter().filter_map(|item| match item {
+        SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => Some(expr),
+        _ => None,
+    });
+    explain_subqueries(sys_state, favorites, &local_ctes, "SELECT", projected, depth, plan)?;
+    if matches!(select.distinct, Some(Distinct::Distinct)) {
+        plan.push(format!("{}Remove duplicate rows (DISTINCT)", pad));
+    }
+    if let Some(order_by) = &query.order_by {
+        plan.push(format!("{}{}", pad, order_by));
+        if let OrderByKind::Expressions(exprs) = &order_by.kind {
+            explain_subqueries(sys_state, favorites, &local_ctes, "ORDER BY", exprs.iter().map(|o| &o.expr), depth, plan)?;
+        }
+    }
+    if let Some(limit) = &query.limit_clause {
+        plan.push(format!("{}{}", pad, limit.to_string().trim()));
+    }
+    Ok(())
+}
+
+/// Lists the subqueries in a clause's expressions and explains each one. They run
+/// once, before any row is checked (see `inline_subqueries`).
+fn explain_subqueries<'a>(
+    sys_state: &FileSystemState,
+    favorites: &FavoritesManager,
+    ctes: &Ctes,
+    clause: &str,
+    exprs: impl IntoIterator<Item = &'a Expr>,
+    depth: usize,
+    plan: &mut Vec<String>,
+) -> Result<(), String> {
+    let mut found = Vec::new();
+    for expr in exprs {
+        // Stands in for running each one: a single column and no rows.
+        inline_expr(&mut expr.clone(), &mut |query: &Query| {
+            found.push(query.clone());
+            Ok((vec![String::new()], Vec::new()))
+        })?;
+    }
+    let pad = "  ".repeat(depth);
+    for query in found {
+        plan.push(format!("{}Subquery in {} (runs once): ({})", pad, clause, query));
+        explain_query(sys_state, favorites, ctes, &query, depth + 1, plan)?;
+    }
+    Ok(())
+}
+
+/// Describes a system table or data file, with the columns known before it is
+/// read, so EXPLAIN does not have to read it. None for every other table.
+fn unread_table(
+    sys_state: &FileSystemState,
+    ctes: &Ctes,
+    name: &ObjectName,
+    args: Option<&TableFunctionArgs>,
+) -> Result<Option<(String, Vec<String>)>, String> {
+    let ident = match name.0.as_slice() {
+        [part] => part.as_ident(),
+        _ => None,
+    };
+    let Some(ident) = ident else {
+        return Ok(None);
+    };
+    let table = ident.value.to_lowercase();
+    Ok(match args {
+        None if ident.quote_style.is_none() && !ctes.contains_key(&table) => crate::system_tables::columns(&table)
+            .map(|columns| {
+                let description = format!("Read system table {} (when the query runs)", table);
+                (description, columns.iter().map(|c| c.to_string()).collect())
+            }),
+        // The columns of a data file are only known once it is parsed.
+        Some(args) if matches!(table.as_str(), "csv" | "json" | "jsonl") => {
+            let (path, _, _) = data_file_args(&table, args, sys_state.get_current_path())?;
+            Some((format!("Parse {} file {} (when the query runs)", table.to_uppercase(), path.display()), Vec::new()))
+        }
+        _ => None,
+    })
+}
+
+fn explain_source(source: &Source, depth: usize, plan: &mut Vec<String>) {
+    let pad = "  ".repeat(depth);
+    match source {
+        Source::Files(files) => explain_walk(files, depth, plan),
+        Source::Lines { files, binary } => {
+            let which = if *binary { "every file" } else { "every text file (binary files are skipped)" };
+            plan.push(format!("{}Read each line of {} in:", pad, which));
+            explain_walk(files, depth + 1, plan);
+        }
+        Source::Data { origin, .. } => plan.push(format!("{}Read the rows of {}", pad, origin)),
+        Source::Join { .. } => {}
+    }
+}
+
+fn explain_walk(files: &FileSource, depth: usize, plan: &mut Vec<String>) {
+    let pad = "  ".repeat(depth);
+    let levels = match files.max_depth() {
+        Some(1) => "only the entries directly inside".to_string(),
+        Some(n) => format!("down to depth {}", n),
+        None => "every level below (no depth limit)".to_string(),
+    };
+    let links = if files.follow_links { ", following symlinks" } else { "" };
+    match crate::indexer::covering_root(&files.root).filter(|_| !files.follow_links) {
+        Some(indexed) => plan.push(format!(
+            "{}Read {} from the index of {} (re-reads changed directories and re-checks every entry's stat): {}",
+            pad,
+            files.root.display(),
+            indexed.display(),
+            levels
+        )),
+        None => plan.push(format!("{}Walk {}: {}{}", pad, files.root.display(), levels, links)),
+    }
+    if let Some(limit) = files.pushdown.max_depth
+        && files.max_depth.is_none_or(|table| limit < table)
+    {
+        plan.push(format!("{}  Depth limit {} comes from the WHERE clause", pad, limit));
+    }
+    for (column, prefix) in &files.pushdown.prefixes {
+        plan.push(format!("{}  Skips directories that cannot contain a {} starting with '{}'", pad, column, prefix));
+    }
+    if files.max_depth().is_none() && files.root.parent().is_none() {
+        plan.push(format!("{}  Warning: walks the whole filesystem; add a depth limit or a path condition", pad));
+    }
+}
+
+/// The column names a query's SELECT list produces, as far as they can be told
+/// without running it (`*` expands to nothing).
+fn select_labels(query: &Query) -> Vec<String> {
+    let SetExpr::Select(select) = &*query.body else {
+        return Vec::new();
+    };
+    select
+        .projection
+        .iter()
+        .filter_map(|item| match item {
+            SelectItem::UnnamedExpr(expr) => Some(expr.to_string()),
+            SelectItem::ExprWithAlias { alias, .. } => Some(alias.value.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The content columns a query mentions outside of string literals.
+fn content_columns_used(query: &Query) -> Vec<&'static str> {
+    let sql = query.to_string();
+    let tokens = Tokenizer::new(&GenericDialect {}, &sql).tokenize().unwrap_or_default();
+    CONTENT_COLUMNS
+        .iter()
+        .copied()
+        .filter(|column| {
+            tokens.iter().any(|token| matches!(token, Token::Word(w) if w.quote_style.is_none() && w.value.eq_ignore_ascii_case(column)))
+        })
+        .collect()
+}
+
+/// Runs a SELECT and returns its column names and rows, both in SELECT order.
+/// `ctes` are the WITH queries of the statements around it.
+fn run_select(
+    sys_state: &FileSystemState,
+    favorites: &FavoritesManager,
+    ctes: &Ctes,
+    query: &Query,
+) -> Result<(Vec<String>, Vec<Projected>), String> {
+    // Each WITH query runs once, in order, and can read the ones before it.
+    let mut local_ctes;
+    let ctes = match &query.with {
+        Some(with) => {
+            if with.recursive {
+                return Err("WITH RECURSIVE is not supported".to_string());
+            }
+            local_ctes = ctes.clone();
+            for cte in &with.cte_tables {
+                let name = &cte.alias.name.value;
+                let (columns, rows) = run_select(sys_state, favorites, &local_ctes, &cte.query)?;
+                let renamed: Vec<String> = cte.alias.columns.iter().map(|c| c.name.value.clone()).collect();
+                local_ctes.insert(name.to_lowercase(), named_rows(name, &renamed, columns, rows)?);
+            }
+            &local_ctes
+        }
+        None => ctes,
+    };
+
+    let query = &inline_query_subqueries(sys_state, favorites, ctes, query)?;
+    let SetExpr::Select(select) = &*query.body else {
+        return Err("Unsupported query type".to_string());
+    };
+    let mut source = resolve_from(sys_state, favorites, ctes, &select.from)?;
+    source.push_down(select.selection.as_ref());
+
+    let aggregate = is_aggregate_query(select);
+    if let Some(selection) = &select.selection
+        && contains_aggregate(selection)
+    {
+        return Err("Aggregate functions are not allowed in WHERE; use HAVING instead".to_string());
+    }
+    if select.having.is_some() && !aggregate {
+        return Err("HAVING requires GROUP BY or an aggregate function".to_string());
+    }
+    let order_by = match &query.order_by {
+        Some(order_by) => order_exprs(order_by)?,
+        None => &[],
+    };
+
+    // Evaluate the clauses once against an all-NULL row so unknown columns are
+    // reported even when the directory is empty.
+    let columns = source.columns();
+    let probe = probe_row(&columns);
+    let probe_scope = Scope { row: &probe, group: aggregate.then_some(&[]) };
+    if let Some(selection) = &select.selection {
+        evaluate_expr(selection, &Scope { row: &probe, group: None })?;
+    }
+    if let Some(having) = &select.having {
+        evaluate_expr(having, &probe_scope)?;
+    }
+    let (_, probe_output) = output_row(select, order_by, &columns, &probe_scope)?;
+    let output_columns = unique_labels(probe_output.into_iter().map(|(column, _)| column).collect());
+    let (offset, limit) = limit_and_offset(query)?;
+    let distinct = match &select.distinct {
+        None | Some(Distinct::All) => false,
+        Some(Distinct::Distinct) => true,
+        Some(_) => return Err("DISTINCT ON is not supported; use SELECT DISTINCT".to_string()),
+    };
+
+    let matched = scan(&source, select.selection.as_ref())?;
+
+    let mut results = Vec::new();
+    if aggregate {
+        for (representative, members) in group_rows(select, matched, &probe)? {
+            let scope = Scope { row: &representative, group: Some(&members) };
+            if let Some(having) = &select.having
+                && !evaluate_expr(having, &scope)?
+            {
+                continue;
+            }
+            results.push(output_row(select, order_by, &columns, &scope)?);
+        }
+    } else {
+        for row in &matched {
+            results.push(output_row(select, order_by, &columns, &Scope { row, group: None })?);
+        }
+    }
+
+    if distinct {
+        let mut seen = HashSet::new();
+        results.retain(|(_, projected)| seen.insert(format!("{:?}", projected.iter().map(|(_, v)| v).collect::<Vec<_>>())));
+    }
+    if !order_by.is_empty() {
+        sort_results(&mut results, order_by);
+    }
+
+    let rows = results
+        .into_iter()
+        .skip(offset)
+        .take(limit.unwrap_or(usize::MAX))
+        // Labels by position, so a duplicate takes its renamed label.
+        .map(|(_, projected)| output_columns.iter().cloned().zip(projected.into_iter().map(|(_, v)| v)).collect())
+        .collect();
+    Ok((output_columns, rows))
+}
+
+/// Output labels with repeats renamed, e.g. `SELECT size, size` gives `size`
+/// and `size_2`, so no column hides another in the output or in an outer query.
+fn unique_labels(labels: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    labels
+        .into_iter()
+        .map(|label| {
+            let mut unique = label.clone();
+            let mut n = 1;
+            while !seen.insert(unique.to_lowercase()) {
+                n += 1;
+                unique = format!("{}_{}", label, n);
+            }
+            unique
+        })
+        .collect()
+}
+
+/// Turns a query's output into table rows, renamed to the columns given in
+/// `WITH name (a, b)` or `CREATE VIEW name (a, b)` if there are any.
+fn named_rows(name: &str, renamed: &[String], mut columns: Vec<String>, rows: Vec<Projected>) -> Result<(Vec<String>, Vec<Row>), String> {
+    if !renamed.is_empty() {
+        if renamed.len() != columns.len() {
+            return Err(format!("'{}' names {} columns but its query returns {}", name, renamed.len(), columns.len()));
+        }
+        columns = renamed.to_vec();
+    }
+    let rows = rows
+        .into_iter()
+        .map(|projected| columns.iter().cloned().zip(projected.into_iter().map(|(_, v)| v)).collect())
+        .collect();
+    Ok((columns, rows))
+}
+
+/// Runs the subqueries of a SELECT's expressions (see `inline_subqueries`).
+fn inline_query_subqueries(sys_state: &FileSystemState, favorites: &FavoritesManager, ctes: &Ctes, query: &Query) -> Result<Query, String> {
+    let mut query = query.clone();
+    let mut run = |q: &Query| run_select(sys_state, favorites, ctes, q);
+    if let SetExpr::Select(select) = &mut *query.body {
+        for expr in select.selection.iter_mut().chain(select.having.iter_mut()) {
+            inline_expr(expr, &mut run)?;
+        }
+        for item in &mut select.projection {
+            match item {
+                SelectItem::UnnamedExpr(expr) => {
+                    // Keep the column named after the subquery, not its result.
+                    let label = expr.to_string();
+                    if inline_expr(expr, &mut run)? {
+                        let expr = expr.clone();
+                        *item = SelectItem::ExprWithAlias { expr, alias: Ident::new(label) };
+                    }
+                }
+                SelectItem::ExprWithAlias { expr, .. } => {
+                    inline_expr(expr, &mut run)?;
+                }
+                _ => {}
+            }
+        }
+    }
+    if let Some(OrderBy { kind: OrderByKind::Expressions(exprs), .. }) = &mut query.order_by {
+        for order in exprs {
+            inline_expr(&mut order.expr, &mut run)?;
+        }
+    }
+    Ok(query)
+}
+
+/// Replaces the subqueries in an expression with their results, so evaluating a
+/// row never runs a query: `x IN (SELECT ...)` becomes a list of values and
+/// `(SELECT MAX(size) ...)` or `EXISTS (...)` a single value. Subqueries run
+/// once and cannot refer to the columns of the outer query.
+fn inline_subqueries(sys_state: &FileSystemState, favorites: &FavoritesManager, ctes: &Ctes, expr: &Expr) -> Result<Expr, String> {
+    let mut expr = expr.clone();
+    inline_expr(&mut expr, &mut |q: &Query| run_select(sys_state, favorites, ctes, q))?;
+    Ok(expr)
+}
+
+type RunQuery<'a> = dyn FnMut(&Query) -> Result<(Vec<String>, Vec<Projected>), String> + 'a;
+
+/// Returns whether anything was replaced.
+fn inline_expr(expr: &mut Expr, run: &mut RunQuery) -> Result<bool, String> {
+    let replacement = match expr {
+        Expr::InSubquery { expr: target, subquery, negated } => {
+            inline_expr(target, run)?;
+            let list = subquery_values(subquery, run)?.into_iter().map(literal).collect();
+            Expr::InList { expr: target.clone(), list, negated: *negated }
+        }
+        Expr::Subquery(subquery) => {
+            let mut values = subquery_values(subquery, run)?;
+            if values.len() > 1 {
+                return Err(format!("Subquery '{}' returned {} rows where one value is expected", subquery, values.len()));
+            }
+            literal(values.pop().unwrap_or(TypedValue::Null))
+        }
+        Expr::Exists { subquery, negated } => {
+            let (_, rows) = run(subquery)?;
+            Expr::value(Value::Boolean(rows.is_empty() == *negated))
+        }
+        Expr::BinaryOp { left, right, .. } => return Ok(inline_expr(left, run)? | inline_expr(right, run)?),
+        Expr::Like { expr, pattern, .. } | Expr::ILike { expr, pattern, .. } | Expr::RLike { expr, pattern, .. } => {
+            return Ok(inline_expr(expr, run)? | inline_expr(pattern, run)?);
+        }
+        Expr::Between { expr, low, high, .. } => {
+            return Ok(inline_expr(expr, run)? | inline_expr(low, run)? | inline_expr(high, run)?);
+        }
+        Expr::InList { expr, list, .. } => {
+            let mut changed = inline_expr(expr, run)?;
+            for item in list {
+                changed |= inline_expr(item, run)?;
+            }
+            return Ok(changed);
+        }
+        Expr::UnaryOp { expr, .. }
+        | Expr::Nested(expr)
+        | Expr::IsNull(expr)
+        | Expr::IsNotNull(expr)
+        | Expr::IsTrue(expr)
+        | Expr::IsNotTrue(expr)
+        | Expr::IsFalse(expr)
+        | Expr::IsNotFalse(expr) => return inline_expr(expr, run),
+        Expr::Function(func) => {
+            let mut changed = false;
+            if let FunctionArguments::List(list) = &mut func.args {
+                for arg in &mut list.args {
+                    if let FunctionArg::Unnamed(FunctionArgExpr::Expr(e)) | FunctionArg::Named { arg: FunctionArgExpr::Expr(e), .. } = arg {
+                        changed |= inline_expr(e, run)?;
+                    }
+                }
+            }
+            return Ok(changed);
+        }
+        _ => return Ok(false),
+    };
+    *expr = replacement;
+    Ok(true)
+}
+
+/// The values of a one-column subquery.
+fn subquery_values(subquery: &Query, run: &mut RunQuery) -> Result<Vec<TypedValue>, String> {
+    let (columns, rows) = run(subquery)?;
+    if columns.len() != 1 {
+        return Err(format!("Subquery '{}' must select exactly one column", subquery));
+    }
+    Ok(rows.into_iter().filter_map(|row| row.into_iter().next().map(|(_, v)| v)).collect())
+}
+
+/// A SQL literal for a computed value that evaluates back to exactly that value:
+/// timestamps keep their nanoseconds and intervals stay intervals.
+fn literal(value: TypedValue) -> Expr {
+    let text = |s: String| Expr::value(Value::SingleQuotedString(s));
+    match value {
+        TypedValue::Number(n) => Expr::value(Value::Number(n.to_string(), false)),
+        TypedValue::Boolean(b) => Expr::value(Value::Boolean(b)),
+        TypedValue::Null => Expr::value(Value::Null),
+        TypedValue::Text(s) => text(s),
+        TypedValue::Timestamp(t) => Expr::TypedString(TypedString {
+            data_type: DataType::Timestamp(None, TimezoneInfo::WithTimeZone),
+            value: Value::SingleQuotedString(t.to_rfc3339_opts(chrono::SecondsFormat::Nanos, false)).into(),
+            uses_odbc_syntax: false,
+        }),
+        TypedValue::Interval(d) => Expr::Interval(Interval {
+            value: Box::new(text(format!("{} seconds {} nanoseconds", d.num_seconds(), d.subsec_nanos()))),
+            leading_field: None,
+            leading_precision: None,
+            last_field: None,
+            fractional_seconds_precision: None,
+        }),
+    }
+}
+
+/// A row with every column NULL, used to validate expressions before scanning.
+fn probe_row(columns: &[String]) -> Row {
+    columns.iter().map(|c| (c.clone(), TypedValue::Null)).collect()
+}
+
+/// Walks `source` and returns the rows that pass the WHERE clause.
+fn scan(source: &Source, selection: Option<&Expr>) -> Result<Vec<Row>, String> {
+    let mut matched = Vec::new();
+    let mut keep = |row: Row| -> Result<(), String> {
+        if walker::interrupted() {
+            return Err(walker::CANCELLED.to_string());
+        }
+        // Implement WHERE clause filtering
+        if let Some(selection) = selection
+            && !evaluate_expr(selection, &Scope { row: &row, group: None })?
+        {
+            return Ok(());
+        }
+        matched.push(row);
+        Ok(())
+    };
+    // The same check on the walker's threads, so rows WHERE rules out are dropped
+    // as soon as they are built instead of piling up first.
+    let check = |row: Row| -> Option<Result<Row, String>> {
+        match selection.map(|selection| evaluate_expr(selection, &Scope { row: &row, group: None })) {
+            Some(Ok(false)) => None,
+            Some(Err(e)) => Some(Err(e)),
+            _ => Some(Ok(row)),
+        }
+    };
+
+    match source {
+        Source::Files(files) => match indexed_rows(files) {
+            Some(rows) => {
+                for row in rows {
+                    keep(row)?;
+                }
+            }
+            None => {
+                let rows = walk(files, |entry| check(file_row(entry, &files.root)))?;
+                matched = rows.into_iter().collect::<Result<_, _>>()?;
+            }
+        },
+        Source::Lines { files, binary } => {
+            // Files are read and their lines checked on the walker's threads.
+            let files_lines = walk(files, |entry| {
+                if entry.file_type().is_dir() {
+                    return None;
+                }
+                let bytes = std::fs::read(entry.path()).ok()?;
+                // Same heuristic as git and grep: a NUL byte near the start means binary.
+                if !binary && bytes.iter().take(8000).any(|b| *b == 0) {
+                    return None;
+                }
+                let path = entry.path().to_string_lossy().to_string();
+                let relative = match entry.path().strip_prefix(&files.root) {
+                    Ok(r) if !r.as_os_str().is_empty() => r.to_string_lossy().to_string(),
+                    _ => entry.file_name().to_string_lossy().to_string(),
+                };
+                let name = entry.file_name().to_string_lossy().to_string();
+                let rows: Vec<Result<Row, String>> = String::from_utf8_lossy(&bytes)
+                    .lines()
+                    .enumerate()
+                    .filter_map(|(i, line)| {
+                        let mut row = Row::new();
+                        row.insert("path".to_string(), TypedValue::Text(path.clone()));
+                        row.insert("relative_path".to_string(), TypedValue::Text(relative.clone()));
+                        row.insert("name".to_string(), TypedValue::Text(name.clone()));
+                        row.insert("line_no".to_string(), TypedValue::Number((i + 1) as f64));
+                        row.insert("text".to_string(), TypedValue::Text(line.to_string()));
+                        check(row)
+                    })
+                    .collect();
+                Some(rows)
+            })?;
+            matched = files_lines.into_iter().flatten().collect::<Result<_, _>>()?;
+        }
+        Source::Data { rows, .. } => {
+            for row in rows {
+                keep(row.clone())?;
+            }
+        }
+        Source::Join { name, first, joins } => {
+            let mut rows: Vec<Row> = scan(first, None)?.into_iter().map(|row| qualify_row(name, row)).collect();
+            let mut columns = qualify_columns(name, first.columns());
+            for step in joins {
+                let right = scan(&step.source, None)?.into_iter().map(|row| qualify_row(&step.name, row)).collect();
+                let right_columns = qualify_columns(&step.name, step.source.columns());
+                rows = join_rows(rows, &columns, right, &right_columns, step)?;
+                columns.extend(right_columns);
+            }
+            for row in rows {
+                keep(row)?;
+            }
+        }
+    }
+    Ok(matched)
+}
+
+/// Joins two sets of qualified rows. Rows of an outer join without a partner
+/// get NULL for every column of the other side.
+fn join_rows(
+    left: Vec<Row>,
+    left_columns: &[String],
+    right: Vec<Row>,
+    right_columns: &[String],
+    step: &JoinStep,
+) -> Result<Vec<Row>, String> {
+    // For `ON a.x = b.y` only the right rows with the same key are tried, instead
+    // of every pair. The condition is still evaluated, so the index only has to
+    // group rows that might match.
+    let index = step.on.as_ref().and_then(|on| equi_join_columns(on, left_columns, right_columns)).map(|(l, r)| {
+        let mut index: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, row) in right.iter().enumerate() {
+            if let Some(key) = row.get(&r).and_then(join_key) {
+                index.entry(key).or_default().push(i);
+            }
+        }
+        (l, index)
+    });
+
+    let mut joined = Vec::new();
+    let mut right_matched = vec![false; right.len()];
+    for left_row in left {
+        let candidates = match &index {
+            Some((column, index)) => left_row
+                .get(column)
+                .and_then(join_key)
+                .and_then(|key| index.get(&key))
+                .cloned()
+                .unwrap_or_default(),
+            None => (0..right.len()).collect(),
+        };
+        let mut matched = false;
+        for i in candidates {
+            let mut row = left_row.clone();
+            row.extend(right[i].iter().map(|(c, v)| (c.clone(), v.clone())));
+            if let Some(on) = &step.on
+                && !evaluate_expr(on, &Scope { row: &row, group: None })?
+            {
+                continue;
+            }
+            matched = true;
+            right_matched[i] = true;
+            joined.push(row);
+        }
+        if !matched && matches!(step.kind, JoinKind::Left | JoinKind::Full) {
+            let mut row = left_row;
+            row.extend(probe_row(right_columns));
+            joined.push(row);
+        }
+    }
+    if matches!(step.kind, JoinKind::Right | JoinKind::Full) {
+        for (row, matched) in right.into_iter().zip(right_matched) {
+            if !matched {
+                let mut padded = probe_row(left_columns);
+                padded.extend(row);
+                joined.push(padded);
+            }
+        }
+    }
+    Ok(joined)
+}
+
+/// For a condition like `a.x = b.y` (possibly ANDed with others), the left and
+/// right row keys it compares.
+fn equi_join_columns(on: &Expr, left_columns: &[String], right_columns: &[String]) -> Option<(String, String)> {
+    match on {
+        Expr::Nested(inner) => equi_join_columns(inner, left_columns, right_columns),
+        Expr::BinaryOp { left, op: BinaryOperator::And, right } => equi_join_columns(left, left_columns, right_columns)
+            .or_else(|| equi_join_columns(right, left_columns, right_columns)),
+        Expr::BinaryOp { left, op: BinaryOperator::Eq, right } => {
+            let key = |expr: &Expr, columns: &[String]| match expr {
+                Expr::CompoundIdentifier(parts) if parts.len() == 2 => {
+                    let key = format!("{}.{}", parts[0].value, parts[1].value);
+                    columns.iter().find(|c| c.eq_ignore_ascii_case(&key)).cloned()
+                }
+                _ => None,
             };
+            match (key(left, left_columns), key(right, right_columns)) {
+                (Some(l), Some(r)) => Some((l, r)),
+                _ => Some((key(right, left_columns)?, key(left, right_columns)?)),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The index key of a join column. Numbers and numeric text share a key
+/// because `compare` treats them as equal; NULL never matches.
+fn join_key(value: &TypedValue) -> Option<String> {
+    match value {
+        TypedValue::Null => None,
+        TypedValue::Number(n) => Some(n.to_string()),
+        TypedValue::Text(s) => Some(s.trim().parse::<f64>().map_or_else(|_| s.clone(), |n| n.to_string())),
+        other => other.as_text(),
+    }
+}
+
+/// Maps the entries below a directory table's root in parallel, skipping
+/// unreadable ones. A file root (only possible through `lines('<file>')`)
+/// yields just that file.
+fn walk<T: Send>(files: &FileSource, map: impl Fn(&Entry) -> Option<T> + Sync) -> Result<Vec<T>, String> {
+    let options = WalkOptions {
+        min_depth: if files.root.is_file() { 0 } else { 1 },
+        max_depth: files.max_depth(),
+        follow_links: files.follow_links,
+    };
+    let prune = |entry: &Entry| entry.depth() == 0 || !entry.file_type().is_dir() || files.pushdown.may_contain(entry.path(), &files.root);
+    walker::walk(&files.root, &options, prune, map)
+}
+
+/// `DELETE FROM <table> WHERE ...`: lists the matching entries, then deletes them
+/// once the user confirms. Directories are only removed with a trailing CASCADE.
+fn execute_delete(sys_state: &mut FileSystemState, favorites: &FavoritesManager, delete: &Delete, dry_run: bool, cascade: bool) -> Result<CellValue, String> {
+    let matched = delete_targets(sys_state, favorites, delete)?;
+    if matched.is_empty() {
+        return Ok(CellValue::String("No matching entries; nothing deleted".to_string()));
+    }
+
+    let directories = matched.iter().filter(|row| row["is_dir"] == TypedValue::Boolean(true)).count();
+    crate::cprintln!("{}", preview_table(&matched, &["relative_path", "is_dir", "size", "modified"]).to_string());
+
+    if directories > 0 && !cascade {
+        return Err(format!(
+            "{} of the matching entries are directories. Add CASCADE to delete directories and their contents, or exclude them with AND NOT is_dir",
+            directories
+        ));
+    }
+    if dry_run {
+        return Ok(CellValue::String(format!("Dry run: {} entries would be deleted", matched.len())));
+    }
+    if !confirm(&format!("Delete {} entries?", matched.len())) {
+        return Ok(CellValue::String("Delete cancelled".to_string()));
+    }
+    remove_entries(&matched)
+}
+
+/// The entries a DELETE matches, children before their parents.
+fn delete_targets(sys_state: &FileSystemState, favorites: &FavoritesManager, delete: &Delete) -> Result<Vec<Row>, String> {
+    let tables = match &delete.from {
+        FromTable::WithFromKeyword(tables) | FromTable::WithoutKeyword(tables) => tables,
+    };
+    let [table] = tables.as_slice() else {
+        return Err("DELETE works on exactly one table".to_string());
+    };
+    if !table.joins.is_empty() || delete.using.is_some() {
+        return Err("DELETE does not support joins".to_string());
+    }
+    let ctes = &Ctes::new();
+    let mut source = resolve_source(sys_state, favorites, ctes, &table.relation)?;
+    if !matches!(source, Source::Files(_)) {
+        return Err("DELETE only works on directory tables".to_string());
+    }
+
+    let selection = match &delete.selection {
+        Some(selection) => Some(inline_subqueries(sys_state, favorites, ctes, selection)?),
+        None => None,
+    };
+    if let Some(selection) = &selection {
+        if contains_aggregate(selection) {
+            return Err("Aggregate functions are not allowed in WHERE".to_string());
+        }
+        evaluate_expr(selection, &Scope { row: &probe_row(&source.columns()), group: None })?;
+    }
+
+    source.push_down(selection.as_ref());
+    source.bypass_index();
+    let mut matched = scan(&source, selection.as_ref())?;
+    // Children first, so a CASCADE delete never removes a directory before its listed contents.
+    matched.sort_by(|a, b| compare_for_sort(&b["depth"], &a["depth"], true, None));
+    Ok(matched)
+}
+
+/// Deletes the entries in the order given and reports how many went.
+fn remove_entries(matched: &[Row]) -> Result<CellValue, String> {
+    let mut deleted = 0;
+    let mut failures = Vec::new();
+    for row in matched {
+        let path = PathBuf::from(row["path"].as_text().unwrap_or_default());
+        // Already gone because a listed parent directory was deleted first.
+        if std::fs::symlink_metadata(&path).is_err() {
+            continue;
+        }
+        let result = if row["is_dir"] == TypedValue::Boolean(true) && row["is_symlink"] != TypedValue::Boolean(true) {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        };
+        match result {
+            Ok(()) => deleted += 1,
+            Err(e) => failures.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(CellValue::String(format!("Deleted {} entries", deleted)))
+    } else {
+        Err(format!("Deleted {} entries; {} failed:\n{}", deleted, failures.len(), failures.join("\n")))
+    }
+}
+
+/// Columns UPDATE can assign: `name` and `ext` rename, `parent` moves, `mode` chmods.
+const UPDATABLE_COLUMNS: &[&str] = &["name", "ext", "parent", "mode"];
+
+/// One entry an UPDATE will change.
+struct PlannedUpdate {
+    from: PathBuf,
+    to: PathBuf,
+    /// Old and new permission bits, when they differ.
+    mode: Option<(Option<u32>, u32)>,
+}
+
+/// `UPDATE <table> SET ... WHERE ...`: computes every rename, move and chmod up
+/// front, refuses the whole batch on collisions, previews it and asks first.
+fn execute_update(sys_state: &mut FileSystemState, favorites: &FavoritesManager, update: &Update, dry_run: bool) -> Result<CellValue, String> {
+    let plan = plan_update(sys_state, favorites, update)?;
+    if plan.changes.is_empty() {
+        return Ok(CellValue::String(format!("{} matching entries; nothing to change", plan.matched)));
+    }
+
+    check_update_collisions(&plan.changes)?;
+
+    let show = |path: &Path| path.strip_prefix(&plan.root).unwrap_or(path).to_string_lossy().to_string();
+    let preview = plan
+        .changes
+        .iter()
+        .map(|change| {
+            let mut cells = HashMap::new();
+            cells.insert("from".to_string(), CellValue::String(show(&change.from)));
+            cells.insert("to".to_string(), CellValue::String(show(&change.to)));
+            if plan.sets_mode {
+                let mode = match change.mode {
+                    Some((old, new)) => format!("{} -> {:o}", old.map_or("?".to_string(), |m| format!("{:o}", m)), new),
+                    None => "unchanged".to_string(),
+                };
+                cells.insert("mode".to_string(), CellValue::String(mode));
+            }
+            cells
+        })
+        .collect();
+    let mut columns = vec!["from".to_string(), "to".to_string()];
+    if plan.sets_mode {
+        columns.push("mode".to_string());
+    }
+    crate::cprintln!("{}", CellValue::Table(preview, columns).to_string());
+
+    if dry_run {
+        return Ok(CellValue::String(format!("Dry run: {} entries would be updated", plan.changes.len())));
+    }
+    if !confirm(&format!("Update {} entries?", plan.changes.len())) {
+        return Ok(CellValue::String("Update cancelled".to_string()));
+    }
+
+    apply_updates(&plan.changes)
+}
+
+/// What an UPDATE will change, worked out before touching anything.
+struct UpdatePlan {
+    /// The table's directory; the preview shows paths relative to it.
+    root: PathBuf,
+    matched: usize,
+    /// Whether `mode` is assigned, so the preview gets a mode column.
+    sets_mode: bool,
+    /// Children before their parents.
+    changes: Vec<PlannedUpdate>,
+}
+
+fn plan_update(sys_state: &FileSystemState, favorites: &FavoritesManager, update: &Update) -> Result<UpdatePlan, String> {
+    if !update.table.joins.is_empty() || update.from.is_some() {
+        return Err("UPDATE does not support joins".to_string());
+    }
+    let ctes = &Ctes::new();
+    let mut source = resolve_source(sys_state, favorites, ctes, &update.table.relation)?;
+    if !matches!(source, Source::Files(_)) {
+        return Err("UPDATE only works on directory tables".to_string());
+    }
+
+    let mut assignments: Vec<(String, &Expr)> = Vec::new();
+    for assignment in &update.assignments {
+        let AssignmentTarget::ColumnName(column) = &assignment.target else {
+            return Err("UPDATE assigns one column at a time, e.g. SET name = LOWER(name)".to_string());
+        };
+        let column = match column.0.as_slice() {
+            [part] => part.as_ident().map(|i| i.value.to_lowercase()).unwrap_or_default(),
+            _ => column.to_string(),
+        };
+        if !UPDATABLE_COLUMNS.contains(&column.as_str()) {
+            return Err(format!("Cannot SET '{}'. Updatable columns: {}", column, UPDATABLE_COLUMNS.join(", ")));
+        }
+        if assignments.iter().any(|(c, _)| *c == column) {
+            return Err(format!("Column '{}' is assigned more than once", column));
+        }
+        assignments.push((column, &assignment.value));
+    }
+
+    let selection = match &update.selection {
+        Some(selection) => Some(inline_subqueries(sys_state, favorites, ctes, selection)?),
+        None => None,
+    };
+    let probe = probe_row(&source.columns());
+    let probe_scope = Scope { row: &probe, group: None };
+    for expr in assignments.iter().map(|(_, e)| *e).chain(selection.as_ref()) {
+        if contains_aggregate(expr) {
+            return Err("Aggregate functions are not allowed in UPDATE".to_string());
+        }
+        eval_value(expr, &probe_scope)?;
+    }
+
+    source.push_down(selection.as_ref());
+    source.bypass_index();
+    let Source::Files(files) = &source else {
+        unreachable!("checked above");
+    };
+    let mut matched = scan(&source, selection.as_ref())?;
+    // Children first: renaming a directory afterwards carries its already-renamed contents along.
+    matched.sort_by(|a, b| compare_for_sort(&b["depth"], &a["depth"], true, None));
+
+    let mut changes = Vec::new();
+    for row in &matched {
+        let scope = Scope { row, group: None };
+        let from = PathBuf::from(row["path"].as_text().unwrap_or_default());
+        let mut name = row["name"].as_text().unwrap_or_default();
+        let mut dir = from.parent().map(Path::to_path_buf).unwrap_or_default();
+        let mut new_ext = None;
+        let mut new_mode = None;
+
+        // Like SQL, every assignment sees the row as it was before the update.
+        for (column, expr) in &assignments {
+            let value = eval_value(expr, &scope)?;
+            match column.as_str() {
+                "name" => name = value.as_text().ok_or("name cannot be set to NULL")?,
+                // NULL drops the extension.
+                "ext" => new_ext = Some(value.as_text()),
+                "parent" => {
+                    let parent = value.as_text().ok_or("parent cannot be set to NULL")?;
+                    dir = expand_path(&parent, &files.root);
+                }
+                _ => new_mode = Some(parse_mode(&value)?),
+            }
+        }
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            return Err(format!("Invalid file name '{}' for {}", name, from.display()));
+        }
+        let mut to = dir.join(&name);
+        if let Some(ext) = new_ext {
+            // set_extension panics on a separator, and a leading dot would double up.
+            if let Some(ext) = ext.as_ref().filter(|e| e.is_empty() || e.starts_with('.') || e.contains('/')) {
+                return Err(format!("Invalid extension '{}' for {} (use NULL to remove it)", ext, from.display()));
+            }
+            to.set_extension(ext.unwrap_or_default());
+        }
+
+        let old_mode = row["mode"].as_text().and_then(|m| u32::from_str_radix(&m, 8).ok());
+        let mode = new_mode.filter(|m| Some(*m) != old_mode).map(|m| (old_mode, m));
+        if to == from && mode.is_none() {
+            continue;
+        }
+        changes.push(PlannedUpdate { from, to, mode });
+    }
+    Ok(UpdatePlan {
+        root: files.root.clone(),
+        matched: matched.len(),
+        sets_mode: assignments.iter().any(|(c, _)| c == "mode"),
+        changes,
+    })
+}
+
+/// Renames, moves and chmods in the order given and reports how many succeeded.
+fn apply_updates(plans: &[PlannedUpdate]) -> Result<CellValue, String> {
+    let mut updated = 0;
+    let mut failures = Vec::new();
+    for plan in plans {
+        let result = (|| {
+            if plan.to != plan.from {
+                if let Some(dir) = plan.to.parent()
+                    && !dir.exists()
+                {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::rename(&plan.from, &plan.to)?;
+            }
+            if let Some((_, mode)) = plan.mode {
+                std::fs::set_permissions(&plan.to, std::fs::Permissions::from_mode(mode))?;
+            }
+            Ok::<(), std::io::Error>(())
+        })();
+        match result {
+            Ok(()) => updated += 1,
+            Err(e) => failures.push(format!("{}: {}", plan.from.display(), e)),
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(CellValue::String(format!("Updated {} entries", updated)))
+    } else {
+        Err(format!("Updated {} entries; {} failed:\n{}", updated, failures.len(), failures.join("\n")))
+    }
+}
+
+/// Columns INSERT can fill. `relative_path` may create nested entries; `name` may not.
+const INSERTABLE_COLUMNS: &[&str] = &["name", "relative_path", "is_dir", "content", "mode"];
+
+/// One entry an INSERT will create.
+struct PlannedInsert {
+    path: PathBuf,
+    is_dir: bool,
+    content: Option<String>,
+    mode: Option<u32>,
+}
+
+/// `INSERT INTO <table> (columns) VALUES (...)` or `INSERT ... SELECT ...`: creates
+/// the listed files and directories below the table's directory. Existing entries
+/// are never overwritten; the whole batch is refused instead.
+fn execute_insert(sys_state: &mut FileSystemState, favorites: &FavoritesManager, insert: &Insert, dry_run: bool) -> Result<CellValue, String> {
+    let TableObject::TableName(table) = &insert.table else {
+        return Err("INSERT INTO expects 'files' or a directory path".to_string());
+    };
+    let Source::Files(FileSource { root, .. }) = resolve_table(sys_state, favorites, &Ctes::new(), table, None)? else {
+        return Err("INSERT INTO expects 'files' or a directory path".to_string());
+    };
+
+    let columns: Vec<String> = insert.columns.iter().map(|c| c.to_string().trim_matches('"').to_lowercase()).collect();
+    if columns.is_empty() {
+        return Err(format!("List the columns to insert, e.g. INSERT INTO files (name, is_dir) VALUES ('build', true). Columns: {}", INSERTABLE_COLUMNS.join(", ")));
+    }
+    for (i, column) in columns.iter().enumerate() {
+        if !INSERTABLE_COLUMNS.contains(&column.as_str()) {
+            return Err(format!("Cannot insert into '{}'. Columns: {}", column, INSERTABLE_COLUMNS.join(", ")));
+        }
+        if columns[..i].contains(column) {
+            return Err(format!("Column '{}' is listed more than once", column));
+        }
+    }
+    match (columns.iter().any(|c| c == "name"), columns.iter().any(|c| c == "relative_path")) {
+        (false, false) => return Err("INSERT needs a name or relative_path column".to_string()),
+        (true, true) => return Err("Use either name or relative_path, not both".to_string()),
+        _ => {}
+    }
+
+    let Some(source) = &insert.source else {
+        return Err("INSERT needs VALUES or a SELECT".to_string());
+    };
+    let rows: Vec<Vec<TypedValue>> = match &*source.body {
+        SetExpr::Values(values) => {
+            let empty = Row::new();
+            let scope = Scope { row: &empty, group: None };
+            let mut rows = Vec::new();
+            for row in &values.rows {
+                rows.push(row.content.iter().map(|expr| eval_value(expr, &scope)).collect::<Result<Vec<_>, _>>()?);
+            }
+            rows
+        }
+        _ => run_select(sys_state, favorites, &Ctes::new(), source)?
+            .1
+            .into_iter()
+            .map(|projected| projected.into_iter().map(|(_, value)| value).collect())
+            .collect(),
+    };
+
+    let mut plans: Vec<PlannedInsert> = Vec::new();
+    for values in rows {
+        if values.len() != columns.len() {
+            return Err(format!("Expected {} values ({}), got {}", columns.len(), columns.join(", "), values.len()));
+        }
+        let mut plan = PlannedInsert { path: PathBuf::new(), is_dir: false, content: None, mode: None };
+        for (column, value) in columns.iter().zip(values) {
+            match column.as_str() {
+                "name" | "relative_path" => {
+                    let text = value.as_text().ok_or_else(|| format!("{} cannot be NULL", column))?;
+                    let relative = Path::new(&text);
+                    let escapes = relative.is_absolute()
+                        || relative.components().any(|c| matches!(c, std::path::Component::ParentDir));
+                    if text.is_empty() || escapes || (column == "name" && text.contains('/')) {
+                        return Err(format!("Invalid {} '{}'", column, text));
+                    }
+                    plan.path = root.join(relative);
+                }
+                "is_dir" => {
+                    plan.is_dir = match value {
+                        TypedValue::Boolean(b) => b,
+                        TypedValue::Null => false,
+                        other => return Err(format!("is_dir expects true or false, got '{}'", other.as_text().unwrap_or_default())),
+                    }
+                }
+                "content" => plan.content = value.as_text(),
+                _ => plan.mode = match value {
+                    TypedValue::Null => None,
+                    value => Some(parse_mode(&value)?),
+                },
+            }
+        }
+        if plan.is_dir && plan.content.is_some() {
+            return Err(format!("{} is a directory and cannot have content", plan.path.display()));
+        }
+        plans.push(plan);
+    }
+    if plans.is_empty() {
+        return Ok(CellValue::String("Nothing to insert".to_string()));
+    }
+
+    let mut problems = Vec::new();
+    for (i, plan) in plans.iter().enumerate() {
+        if plans[..i].iter().any(|p| p.path == plan.path) {
+            problems.push(format!("{} is listed more than once", plan.path.display()));
+        } else if std::fs::symlink_metadata(&plan.path).is_ok() {
+            problems.push(format!("{} already exists", plan.path.display()));
+        }
+    }
+    if !problems.is_empty() {
+        return Err(format!("INSERT aborted, nothing was created:\n  {}", problems.join("\n  ")));
+    }
+
+    if dry_run {
+        let preview = plans
+            .iter()
+            .map(|plan| {
+                let mut cells = HashMap::new();
+                let relative = plan.path.strip_prefix(&root).unwrap_or(&plan.path);
+                cells.insert("relative_path".to_string(), CellValue::String(relative.to_string_lossy().to_string()));
+                cells.insert("is_dir".to_string(), CellValue::Boolean(plan.is_dir));
+                cells.insert("size".to_string(), CellValue::Integer(plan.content.as_ref().map_or(0, |c| c.len()) as i64));
+                cells
+            })
+            .collect();
+        let columns = ["relative_path", "is_dir", "size"].map(String::from).to_vec();
+        crate::cprintln!("{}", CellValue::Table(preview, columns).to_string());
+        return Ok(CellValue::String(format!("Dry run: {} entries would be created", plans.len())));
+    }
+
+    let mut created = 0;
+    let mut failures = Vec::new();
+    for plan in &plans {
+        let result = (|| {
+            if plan.is_dir {
+                std::fs::create_dir_all(&plan.path)?;
+            } else {
+                if let Some(dir) = plan.path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                let mut file = std::fs::OpenOptions::new().write(true).create_new(true).open(&plan.path)?;
+                if let Some(content) = &plan.content {
+                    std::io::Write::write_all(&mut file, content.as_bytes())?;
+                }
+            }
+            if let Some(mode) = plan.mode {
+                std::fs::set_permissions(&plan.path, std::fs::Permissions::from_mode(mode))?;
+            }
+            Ok::<(), std::io::Error>(())
+        })();
+        match result {
+            Ok(()) => created += 1,
+            Err(e) => failures.push(format!("{}: {}", plan.path.display(), e)),
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(CellValue::String(format!("Created {} entries", created)))
+    } else {
+        Err(format!("Created {} entries; {} failed:\n{}", created, failures.len(), failures.join("\n")))
+    }
+}
+
+/// Reads `mode = '644'` (or `mode = 755`) as octal permission bits.
+fn parse_mode(value: &TypedValue) -> Result<u32, String> {
+    let text = value.as_text().ok_or("mode cannot be set to NULL")?;
+    match u32::from_str_radix(text.trim_start_matches("0o"), 8) {
+        Ok(mode) if mode <= 0o7777 => Ok(mode),
+        _ => Err(format!("Invalid mode '{}'. Use octal permissions such as '644' or '755'", text)),
+    }
+}
+
+/// Rejects a batch where two entries end up at the same path, a target already
+/// exists, or a directory would be moved inside itself.
+fn check_update_collisions(plans: &[PlannedUpdate]) -> Result<(), String> {
+    let mut problems = Vec::new();
+    let mut targets: HashMap<&Path, &Path> = HashMap::new();
+    for plan in plans {
+        if let Some(other) = targets.insert(&plan.to, &plan.from) {
+            problems.push(format!("{} and {} would both become {}", other.display(), plan.from.display(), plan.to.display()));
+        }
+        if plan.to == plan.from {
+            continue;
+        }
+        if plan.to.starts_with(&plan.from) {
+            problems.push(format!("{} cannot be moved inside itself", plan.from.display()));
+        } else if let Ok(existing) = std::fs::symlink_metadata(&plan.to) {
+            // On case-insensitive filesystems `A.JPG -> a.jpg` finds the file itself.
+            let same_file = std::fs::symlink_metadata(&plan.from)
+                .is_ok_and(|m| m.dev() == existing.dev() && m.ino() == existing.ino());
+            if !same_file {
+                problems.push(format!("{} already exists", plan.to.display()));
+            }
+        }
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("UPDATE aborted, nothing was changed:\n  {}", problems.join("\n  ")))
+    }
+}
+
+/// Renders the given columns of `rows` as a table, for previews printed before a change.
+fn preview_table(rows: &[Row], columns: &[&str]) -> CellValue {
+    CellValue::Table(
+        rows.iter()
+            .map(|row| columns.iter().map(|c| (c.to_string(), row[*c].to_cell())).collect())
+            .collect(),
+        columns.iter().map(|c| c.to_string()).collect(),
+    )
+}
+
+/// Asks a yes/no question on the terminal; anything but `y`/`yes` means no.
+fn confirm(question: &str) -> bool {
+    use std::io::Write;
+    crate::cprint!("{} [y/N] ", question);
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() || walker::interrupted() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// A directory listing to scan: `files` is the current directory only, while
+/// `tree(path, max_depth, follow_links)` walks a whole subtree.
+struct FileSource {
+    root: PathBuf,
+    max_depth: Option<usize>,
+    follow_links: bool,
+    pushdown: Pushdown,
+    /// Whether rows may come from an INDEX ADD index. DELETE and UPDATE turn it
+    /// off and always walk, so they act on the filesystem as it is.
+    use_index: bool,
+}
+
+/// What the WHERE clause tells the walker before any row is built. Only
+/// conditions ANDed at the top level count, since they must hold for every match.
+#[derive(Default)]
+struct Pushdown {
+    /// From `depth <= N`, `depth < N`, `depth = N` or `depth BETWEEN a AND N`.
+    max_depth: Option<usize>,
+    /// `(column, prefix)` pairs from `relative_path LIKE 'src/%'`,
+    /// `path GLOB '/srv/*'` or `relative_path = 'src/main.rs'`: every match
+    /// starts with the prefix, so directories that cannot contain one are skipped.
+    prefixes: Vec<(&'static str, String)>,
+}
+
+impl Pushdown {
+    fn from_where(selection: Option<&Expr>) -> Pushdown {
+        let mut pushdown = Pushdown::default();
+        let mut pending: Vec<&Expr> = selection.into_iter().collect();
+        while let Some(expr) = pending.pop() {
+            match expr {
+                Expr::Nested(inner) => pending.push(inner),
+                Expr::BinaryOp { left, op: BinaryOperator::And, right } => {
+                    pending.push(left);
+                    pending.push(right);
+                }
+                Expr::BinaryOp { left, op, right } => {
+                    // Read `2 >= depth` as `depth <= 2`.
+                    let (column, op, value) = match (pushdown_column(left), pushdown_column(right)) {
+                        (Some(column), None) => (column, op.clone(), literal_value(right)),
+                        (None, Some(column)) => {
+                            let flipped = match op {
+                                BinaryOperator::Lt => BinaryOperator::Gt,
+                                BinaryOperator::LtEq => BinaryOperator::GtEq,
+                                BinaryOperator::Gt => BinaryOperator::Lt,
+                                BinaryOperator::GtEq => BinaryOperator::LtEq,
+                                other => other.clone(),
+                            };
+                            (column, flipped, literal_value(left))
+                        }
+                        _ => continue,
+                    };
+                    match (column, op, value) {
+                        ("depth", BinaryOperator::LtEq | BinaryOperator::Eq, Some(TypedValue::Number(n))) => {
+                            pushdown.limit_depth(n.floor());
+                        }
+                        ("depth", BinaryOperator::Lt, Some(TypedValue::Number(n))) => pushdown.limit_depth(n.ceil() - 1.0),
+                        ("path" | "relative_path", BinaryOperator::Eq, Some(TypedValue::Text(p))) => {
+                            pushdown.prefixes.push((column, p));
+                        }
+                        ("path" | "relative_path", BinaryOperator::PGCustomBinaryOperator(name), Some(TypedValue::Text(p)))
+                            if name.len() == 1 && name[0].eq_ignore_ascii_case("glob") =>
+                        {
+                            pushdown.add_prefix(column, &p, &['*', '?', '[']);
+                        }
+                        _ => {}
+                    }
+                }
+                Expr::Between { expr, negated: false, high, .. } if pushdown_column(expr) == Some("depth") => {
+                    if let Some(TypedValue::Number(n)) = literal_value(high) {
+                        pushdown.limit_depth(n.floor());
+                    }
+                }
+                Expr::Like { negated: false, any: false, expr, pattern, escape_char: None } => {
+                    if let (Some(column @ ("path" | "relative_path")), Some(TypedValue::Text(p))) =
+                        (pushdown_column(expr), literal_value(pattern))
+                    {
+                        pushdown.add_prefix(column, &p, &['%', '_', '\\']);
+                    }
+                }
+                _ => {}
+            }
+        }
+        pushdown
+    }
+
+    fn limit_depth(&mut self, depth: f64) {
+        let depth = depth.max(0.0) as usize;
+        self.max_depth = Some(self.max_depth.map_or(depth, |d| d.min(depth)));
+    }
+
+    /// Records the literal start of a pattern, up to its first wildcard.
+    fn add_prefix(&mut self, column: &'static str, pattern: &str, wildcards: &[char]) {
+        let prefix = pattern.split(|c| wildcards.contains(&c)).next().unwrap_or_default();
+        if !prefix.is_empty() {
+            self.prefixes.push((column, prefix.to_string()));
+        }
+    }
+
+    /// Whether a directory or anything below it can match every prefix.
+    fn may_contain(&self, dir: &Path, root: &Path) -> bool {
+        self.prefixes.iter().all(|(column, prefix)| {
+            // The same strings `file_row` puts in the row.
+            let value = match *column {
+                "path" => dir.to_string_lossy(),
+                _ => dir.strip_prefix(root).unwrap_or(dir).to_string_lossy(),
+            };
+            value.starts_with(prefix.as_str()) || prefix.starts_with(&format!("{}/", value))
+        })
+    }
+}
+
+/// The column a pushed-down condition tests, if it is one the walker knows.
+fn pushdown_column(expr: &Expr) -> Option<&'static str> {
+    match expr {
+        Expr::Identifier(ident) => ["depth", "path", "relative_path"]
+            .into_iter()
+            .find(|c| ident.value.eq_ignore_ascii_case(c)),
+        _ => None,
+    }
+}
+
+fn literal_value(expr: &Expr) -> Option<TypedValue> {
+    match expr {
+        Expr::Value(_) => eval_value(expr, &Scope { row: &Row::new(), group: None }).ok(),
+        _ => None,
+    }
+}
+
+impl FileSource {
+    fn new(root: PathBuf, max_depth: Option<usize>) -> FileSource {
+        FileSource { root, max_depth, follow_links: false, pushdown: Pushdown::default(), use_index: true }
+    }
+
+    /// The table's own depth limit, tightened by the WHERE clause.
+    fn max_depth(&self) -> Option<usize> {
+        match (self.max_depth, self.pushdown.max_depth) {
+            (Some(table), Some(filter)) => Some(table.min(filter)),
+            (table, filter) => table.or(filter),
+        }
+    }
+}
+
+/// Columns of `lines(...)`, in the order `SELECT *` returns them.
+const LINES_COLUMNS: &[&str] = &["path", "relative_path", "name", "line_no", "text"];
+
+/// A table a query reads from.
+enum Source {
+    /// Directory entries: `files`, `tree(...)` or a directory path.
+    Files(FileSource),
+    /// One row per line of the text files of a directory table, e.g. `lines(tree('src'))`.
+    Lines { files: FileSource, binary: bool },
+    /// Rows loaded up front, e.g. from `csv('data.csv')` or `json('report.json')`.
+    /// `origin` describes them for EXPLAIN.
+    Data { columns: Vec<String>, rows: Vec<Row>, origin: String },
+    /// Joined (or aliased) tables. Their columns are qualified as `alias.column`.
+    Join { name: String, first: Box<Source>, joins: Vec<JoinStep> },
+}
+
+/// A table joined onto the tables before it.
+struct JoinStep {
+    name: String,
+    source: Source,
+    kind: JoinKind,
+    on: Option<Expr>,
+}
+
+/// Which unmatched rows a join keeps, padded with NULLs. `CROSS JOIN` is an
+/// inner join without a condition.
+#[derive(Clone, Copy, PartialEq)]
+enum JoinKind {
+    Inner,
+    Left,
+    Right,
+    Full,
+}
+
+impl Source {
+    /// Lets the walker of a directory table skip what the WHERE clause rules out.
+    fn push_down(&mut self, selection: Option<&Expr>) {
+        if let Source::Files(files) | Source::Lines { files, .. } = self {
+            files.pushdown = Pushdown::from_where(selection);
+        }
+    }
+
+    /// Makes a directory table walk even when it is indexed (see `FileSource::use_index`).
+    fn bypass_index(&mut self) {
+        if let Source::Files(files) = self {
+            files.use_index = false;
+        }
+    }
+
+    /// Column names in the order `SELECT *` returns them.
+    fn columns(&self) -> Vec<String> {
+        let columns = match self {
+            Source::Files(_) => FILES_COLUMNS,
+            Source::Lines { .. } => LINES_COLUMNS,
+            Source::Data { columns, .. } => return columns.clone(),
+            Source::Join { name, first, joins } => {
+                let mut columns = qualify_columns(name, first.columns());
+                for step in joins {
+                    columns.extend(qualify_columns(&step.name, step.source.columns()));
+                }
+                return columns;
+            }
+        };
+        columns.iter().map(|c| c.to_string()).collect()
+    }
+}
+
+fn qualify_columns(table: &str, columns: Vec<String>) -> Vec<String> {
+    columns.into_iter().map(|c| format!("{}.{}", table, c)).collect()
+}
+
+fn qualify_row(table: &str, row: Row) -> Row {
+    row.into_iter().map(|(c, v)| (format!("{}.{}", table, c), v)).collect()
+}
+
+/// Resolves the FROM clause of a SELECT. A single table without an alias is read
+/// as is; joined tables, comma-separated tables (a cross join) and aliased
+/// tables become a `Source::Join`.
+fn resolve_from(sys_state: &FileSystemState, favorites: &FavoritesManager, ctes: &Ctes, from: &[TableWithJoins]) -> Result<Source, String> {
+    let Some(first) = from.first() else {
+        return Err("Missing FROM clause".to_string());
+    };
+    let aliased = matches!(
+        &first.relation,
+        TableFactor::Table { alias: Some(_), .. } | TableFactor::Derived { alias: Some(_), .. }
+    );
+    if from.len() == 1 && first.joins.is_empty() && !aliased {
+        return resolve_source(sys_state, favorites, ctes, &first.relation);
+    }
+
+    let name = table_name(&first.relation)?;
+    let mut names = vec![name.clone()];
+    let mut joins = Vec::new();
+    let mut add = |relation: &TableFactor, kind: JoinKind, on: Option<Expr>| -> Result<(), String> {
+        let name = table_name(relation)?;
+        if names.contains(&name) {
+            return Err(format!("Table name '{}' is used twice; give each table its own alias", name));
+        }
+        names.push(name.clone());
+        joins.push(JoinStep { name, source: resolve_source(sys_state, favorites, ctes, relation)?, kind, on });
+        Ok(())
+    };
+    for (i, table) in from.iter().enumerate() {
+        if i > 0 {
+            add(&table.relation, JoinKind::Inner, None)?;
+        }
+        for join in &table.joins {
+            let (kind, on) = join_kind(&join.join_operator)?;
+            add(&join.relation, kind, on)?;
+        }
+    }
+    Ok(Source::Join { name, first: Box::new(resolve_source(sys_state, favorites, ctes, &first.relation)?), joins })
+}
+
+fn join_kind(operator: &JoinOperator) -> Result<(JoinKind, Option<Expr>), String> {
+    let (kind, constraint) = match operator {
+        JoinOperator::Join(c) | JoinOperator::Inner(c) | JoinOperator::CrossJoin(c) => (JoinKind::Inner, c),
+        JoinOperator::Left(c) | JoinOperator::LeftOuter(c) => (JoinKind::Left, c),
+        JoinOperator::Right(c) | JoinOperator::RightOuter(c) => (JoinKind::Right, c),
+        JoinOperator::FullOuter(c) => (JoinKind::Full, c),
+        _ => return Err("Unsupported join. Use [INNER | LEFT | RIGHT | FULL | CROSS] JOIN".to_string()),
+    };
+    let on = match constraint {
+        JoinConstraint::On(expr) => Some(expr.clone()),
+        JoinConstraint::None => None,
+        _ => return Err("Only JOIN ... ON <condition> is supported, e.g. ON a.relative_path = b.relative_path".to_string()),
+    };
+    Ok((kind, on))
+}
+
+/// The name that qualifies a joined table's columns: its alias, or the table
+/// name itself for plain tables like `files`.
+fn table_name(relation: &TableFactor) -> Result<String, String> {
+    match relation {
+        TableFactor::Table { alias: Some(alias), .. } | TableFactor::Derived { alias: Some(alias), .. } => {
+            Ok(alias.name.value.to_lowercase())
+        }
+        TableFactor::Table { name, args: None, .. } if name.0.len() == 1 => {
+            Ok(name.0[0].as_ident().map_or_else(|| name.to_string(), |i| i.value.clone()).to_lowercase())
+        }
+        other => Err(format!("Give {} an alias to join it, e.g. {} AS a", other, other)),
+    }
+}
+
+fn resolve_source(sys_state: &FileSystemState, favorites: &FavoritesManager, ctes: &Ctes, relation: &TableFactor) -> Result<Source, String> {
+    match relation {
+        TableFactor::Table { name, args, .. } => resolve_table(sys_state, favorites, ctes, name, args.as_ref()),
+        TableFactor::Derived { subquery, lateral: false, .. } => {
+            let (columns, rows) = run_select(sys_state, favorites, ctes, subquery)?;
+            let rows = rows.into_iter().map(|projected| projected.into_iter().collect()).collect();
+            Ok(Source::Data { columns, rows, origin: "subquery".to_string() })
+        }
+        _ => Err("Unsupported FROM clause".to_string()),
+    }
+}
+
+fn resolve_table(sys_state: &FileSystemState, favorites: &FavoritesManager, ctes: &Ctes, name: &ObjectName, args: Option<&TableFunctionArgs>) -> Result<Source, String> {
+    let current_path = sys_state.get_current_path();
+    let ident = match name.0.as_slice() {
+        [part] => part.as_ident(),
+        _ => None,
+    };
+    let Some(ident) = ident else {
+        return Err(format!("Unsupported table '{}'", name));
+    };
+    if let Some((table, view, query)) = view_named(name, args, ctes)? {
+        return enter_view(&table, || {
+            let (columns, rows) = run_select(sys_state, favorites, &Ctes::new(), &query)?;
+            let (columns, rows) = named_rows(&table, &view.columns, columns, rows)?;
+            Ok(Source::Data { columns, rows, origin: format!("view {}", table) })
+        });
+    }
+
+    match (ident.value.to_lowercase().as_str(), args) {
+        (table, None) if ident.quote_style.is_none() && ctes.contains_key(table) => {
+            let (columns, rows) = &ctes[table];
+            Ok(Source::Data { columns: columns.clone(), rows: rows.clone(), origin: format!("WITH {}", table) })
+        }
+        (table, None) if ident.quote_style.is_none() && crate::system_tables::SYSTEM_TABLES.contains(&table) => {
+            system_table(table, sys_state, favorites)
+        }
+        ("files", None) if ident.quote_style.is_none() => Ok(Source::Files(FileSource::new(current_path.clone(), Some(1)))),
+        ("tree", Some(args)) => {
+            const PARAMS: [&str; 3] = ["path", "max_depth", "follow_links"];
+            let usage = "Usage: tree('<path>', <max_depth>, <follow_links>)";
+            let mut source = FileSource::new(current_path.clone(), None);
+            for (i, arg) in args.args.iter().enumerate() {
+                let (param, expr) = match arg {
+                    FunctionArg::Unnamed(FunctionArgExpr::Expr(e)) => (PARAMS.get(i).copied(), e),
+                    FunctionArg::Named { name, arg: FunctionArgExpr::Expr(e), .. } => {
+                        (PARAMS.into_iter().find(|p| name.value.eq_ignore_ascii_case(p)), e)
+                    }
+                    _ => return Err(format!("Invalid argument '{}'. {}", arg, usage)),
+                };
+                let value = eval_value(expr, &Scope { row: &Row::new(), group: None })?;
+                match (param, value) {
+                    (Some("path"), TypedValue::Text(p)) => source.root = expand_path(&p, current_path),
+                    (Some("max_depth"), TypedValue::Number(n)) if n >= 1.0 => source.max_depth = Some(n as usize),
+                    (Some("max_depth"), TypedValue::Null) => source.max_depth = None,
+                    (Some("follow_links"), TypedValue::Boolean(b)) => source.follow_links = b,
+                    _ => return Err(format!("Invalid argument '{}'. {}", arg, usage)),
+                }
+            }
+            if !source.root.is_dir() {
+                return Err(format!("'{}' is not a directory", source.root.display()));
+            }
+            Ok(Source::Files(source))
+        }
+        ("csv" | "json" | "jsonl", Some(args)) => {
+            let function = ident.value.to_lowercase();
+            let (path, delimiter, header) = data_file_args(&function, args, current_path)?;
+            let text = std::fs::read_to_string(&path).map_err(|e| format!("Cannot read '{}': {}", path.display(), e))?;
+            let (columns, rows) = match function.as_str() {
+                "csv" => crate::data_files::parse_csv(&text, delimiter, header),
+                "json" => crate::data_files::parse_json(&text),
+                _ => crate::data_files::parse_jsonl(&text),
+            }
+            .map_err(|e| format!("Cannot parse '{}': {}", path.display(), e))?;
+            Ok(Source::Data { columns, rows, origin: format!("{} file {}", function.to_uppercase(), path.display()) })
+        }
+        ("lines", Some(args)) => {
+            let usage = "Usage: lines(<table>, <binary>), e.g. lines(tree('src')), lines(files) or lines('notes.txt')";
+            let mut files = None;
+            let mut binary = false;
+            for (i, arg) in args.args.iter().enumerate() {
+                let (param, expr) = match arg {
+                    FunctionArg::Unnamed(FunctionArgExpr::Expr(e)) => (["table", "binary"].get(i).copied(), e),
+                    FunctionArg::Named { name, arg: FunctionArgExpr::Expr(e), .. } if name.value.eq_ignore_ascii_case("binary") => {
+                        (Some("binary"), e)
+                    }
+                    _ => return Err(format!("Invalid argument '{}'. {}", arg, usage)),
+                };
+                if param == Some("binary") {
+                    match eval_value(expr, &Scope { row: &Row::new(), group: None })? {
+                        TypedValue::Boolean(b) => binary = b,
+                        _ => return Err(format!("Invalid argument '{}'. {}", arg, usage)),
+                    }
+                    continue;
+                }
+                let inner = match expr {
+                    Expr::Function(func) => {
+                        let FunctionArguments::List(list) = &func.args else {
+                            return Err(usage.to_string());
+                        };
+                        let args = TableFunctionArgs { args: list.args.clone(), settings: None };
+                        resolve_table(sys_state, favorites, ctes, &func.name, Some(&args))?
+                    }
+                    Expr::Identifier(ident) => resolve_table(sys_state, favorites, ctes, &ObjectName::from(vec![ident.clone()]), None)?,
+                    Expr::Value(_) => match eval_value(expr, &Scope { row: &Row::new(), group: None })? {
+                        // A single file, or the files directly inside a directory.
+                        TypedValue::Text(p) => {
+                            let root = expand_path(&p, current_path);
+                            if !root.exists() {
+                                return Err(format!("'{}' does not exist", root.display()));
+                            }
+                            Source::Files(FileSource::new(root, Some(1)))
+                        }
+                        _ => return Err(usage.to_string()),
+                    },
+                    _ => return Err(usage.to_string()),
+                };
+                match inner {
+                    Source::Files(inner) => files = Some(inner),
+                    _ => return Err(format!("lines() reads a directory table. {}", usage)),
+                }
+            }
+            let files = files.ok_or_else(|| usage.to_string())?;
+            Ok(Source::Lines { files, binary })
+        }
+        // Anything else is read as a directory path: `FROM '/var/log'`, `FROM ~/projects`,
+        // `FROM ../sibling` or a plain directory name.
+        (_, None) => {
+            let root = expand_path(&ident.value, current_path);
+            if !root.is_dir() {
+                return Err(format!(
+                    "Unsupported table '{}'. Use 'files', tree('<path>') or the path of an existing directory.",
+                    ident.value
+                ));
+            }
+            Ok(Source::Files(FileSource::new(root, Some(1))))
+        }
+        _ => Err(format!("Unsupported table function '{}'", name)),
+    }
+}
+
+/// The saved view a FROM item names, with its parsed query. Views are found by
+/// unquoted name and give way to the statement's own WITH queries.
+fn view_named(name: &ObjectName, args: Option<&TableFunctionArgs>, ctes: &Ctes) -> Result<Option<(String, View, Query)>, String> {
+    let ident = match name.0.as_slice() {
+        [part] if args.is_none() => part.as_ident(),
+        _ => None,
+    };
+    let Some(ident) = ident.filter(|ident| ident.quote_style.is_none()) else {
+        return Ok(None);
+    };
+    let table = ident.value.to_lowercase();
+    if ctes.contains_key(&table) {
+        return Ok(None);
+    }
+    let Some(view) = crate::views::get(&table)? else {
+        return Ok(None);
+    };
+    let query = match Parser::parse_sql(&GenericDialect {}, &view.query) {
+        Ok(mut ast) if ast.len() == 1 => match ast.remove(0) {
+            Statement::Query(query) => *query,
+            _ => return Err(format!("View '{}' is not a SELECT", table)),
+        },
+        Ok(_) => return Err(format!("View '{}' is not a single SELECT", table)),
+        Err(e) => return Err(format!("View '{}' no longer parses: {}", table, e)),
+    };
+    Ok(Some((table, view, query)))
+}
+
+lazy_static! {
+    /// The views being run, innermost last, so a view that reads itself is an
+    /// error instead of endless recursion.
+    static ref RUNNING_VIEWS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+}
+
+/// Runs `f` for the view `name`, prefixing its errors with the view's name.
+fn enter_view<T>(name: &str, f: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+    {
+        let mut running = RUNNING_VIEWS.lock().map_err(|_| "Views are unavailable".to_string())?;
+        if running.iter().any(|view| view == name) {
+            return Err(format!("View '{}' refers to itself", name));
+        }
+        running.push(name.to_string());
+    }
+    let result = f();
+    if let Ok(mut running) = RUNNING_VIEWS.lock() {
+        running.pop();
+    }
+    result.map_err(|e| format!("In view '{}': {}", name, e))
+}
+
+/// The file, delimiter and header flag given to `csv(...)`, `json(...)` or `jsonl(...)`.
+fn data_file_args(function: &str, args: &TableFunctionArgs, current_path: &Path) -> Result<(PathBuf, u8, bool), String> {
+    let usage = match function {
+        "csv" => "Usage: csv('<file>', <delimiter>, <header>), e.g. csv('data.csv', ';', false)",
+        _ => "Usage: json('<file>') or jsonl('<file>')",
+    };
+    let params: &[&str] = if function == "csv" { &["path", "delimiter", "header"] } else { &["path"] };
+    let mut path = None;
+    let mut delimiter = b',';
+    let mut header = true;
+    for (i, arg) in args.args.iter().enumerate() {
+        let (param, expr) = match arg {
+            FunctionArg::Unnamed(FunctionArgExpr::Expr(e)) => (params.get(i).copied(), e),
+            FunctionArg::Named { name, arg: FunctionArgExpr::Expr(e), .. } => {
+                (params.iter().copied().find(|p| name.value.eq_ignore_ascii_case(p)), e)
+            }
+            _ => return Err(format!("Invalid argument '{}'. {}", arg, usage)),
+        };
+        match (param, eval_value(expr, &Scope { row: &Row::new(), group: None })?) {
+            (Some("path"), TypedValue::Text(p)) => path = Some(expand_path(&p, current_path)),
+            (Some("delimiter"), TypedValue::Text(d)) if d.len() == 1 => delimiter = d.as_bytes()[0],
+            (Some("header"), TypedValue::Boolean(b)) => header = b,
+            _ => return Err(format!("Invalid argument '{}'. {}", arg, usage)),
+        }
+    }
+    Ok((path.ok_or_else(|| usage.to_string())?, delimiter, header))
+}
+
+/// A system table (see `system_tables`) as a source.
+fn system_table(name: &str, sys_state: &FileSystemState, favorites: &FavoritesManager) -> Result<Source, String> {
+    let columns: Vec<String> = crate::system_tables::columns(name)
+        .ok_or_else(|| format!("Unsupported table '{}'", name))?
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+    let rows = crate::system_tables::rows(name, sys_state, favorites)?
+        .into_iter()
+        .map(|values| columns.iter().cloned().zip(values).collect())
+        .collect();
+    Ok(Source::Data { columns, rows, origin: format!("system table {}", name) })
+}
+
+/// What an expression is evaluated against: the current row and, for
+/// aggregate queries, every row of the group it stands for.
+struct Scope<'a> {
+    row: &'a Row,
+    group: Option<&'a [Row]>,
+}
+
+fn file_row(entry: &Entry, root: &Path) -> Row {
+    let stat = entry.metadata().ok().map(|m| IndexedEntry::new(entry.path(), &m, entry.path_is_symlink()));
+    entry_row(entry.path(), root, entry.depth(), entry.path_is_symlink(), stat.as_ref())
+}
+
+/// Builds a directory table row from metadata read just now or kept in the index.
+fn entry_row(path: &Path, root: &Path, depth: usize, is_symlink: bool, stat: Option<&IndexedEntry>) -> Row {
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let is_dir = stat.map_or_else(|| path.is_dir(), |s| s.is_dir);
+    let ext = match path.extension() {
+        Some(ext) if !is_dir => TypedValue::Text(ext.to_string_lossy().to_string()),
+        _ => TypedValue::Null,
+    };
+    let size = stat.map_or(0, |s| s.size);
+
+    let timestamp = |nanos: Option<i64>| nanos.map_or(TypedValue::Null, |n| TypedValue::Timestamp(Local.timestamp_nanos(n)));
+    let modified = timestamp(stat.and_then(|s| s.modified));
+
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let parent = match relative.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_string_lossy().to_string(),
+        _ => ".".to_string(),
+    };
+
+    let mut row = Row::new();
+    row.insert("name".to_string(), TypedValue::Text(name));
+    row.insert("ext".to_string(), ext);
+    row.insert("size".to_string(), TypedValue::Number(size as f64));
+    row.insert("modified".to_string(), modified);
+    row.insert("is_dir".to_string(), TypedValue::Boolean(is_dir));
+    row.insert("path".to_string(), TypedValue::Text(path.to_string_lossy().to_string()));
+    row.insert("relative_path".to_string(), TypedValue::Text(relative.to_string_lossy().to_string()));
+    row.insert("parent".to_string(), TypedValue::Text(parent));
+    row.insert("depth".to_string(), TypedValue::Number(depth as f64));
+
+    let link_target = match stat {
+        Some(s) => s.link_target.clone(),
+        None if is_symlink => std::fs::read_link(path).ok().map(|t| t.to_string_lossy().to_string()),
+        None => None,
+    };
+    row.insert("is_symlink".to_string(), TypedValue::Boolean(is_symlink));
+    row.insert("link_target".to_string(), link_target.map_or(TypedValue::Null, TypedValue::Text));
+
+    match stat {
+        Some(s) => {
+            let mode = s.mode & 0o7777;
+            // Exec bits, or an extension `filesystem::is_executable` accepts, decided from
+            // the stat already at hand so indexed rows are not stat-ed again.
+            let is_executable = s.is_file() && (mode & 0o111 != 0 || crate::filesystem::has_executable_extension(path));
+            row.insert("mode".to_string(), TypedValue::Text(format!("{:o}", mode)));
+            row.insert("perms".to_string(), TypedValue::Text(perms_string(mode)));
+            row.insert("uid".to_string(), TypedValue::Number(s.uid as f64));
+            row.insert("gid".to_string(), TypedValue::Number(s.gid as f64));
+            row.insert("owner".to_string(), TypedValue::Text(crate::system_tables::user_name(s.uid)));
+            row.insert("group".to_string(), TypedValue::Text(crate::system_tables::group_name(s.gid)));
+            row.insert("inode".to_string(), TypedValue::Number(s.inode as f64));
+            row.insert("nlink".to_string(), TypedValue::Number(s.nlink as f64));
+            row.insert("accessed".to_string(), timestamp(s.accessed));
+            row.insert("created".to_string(), timestamp(s.created));
+            row.insert("changed".to_string(), timestamp(Some(s.changed)));
+            row.insert("is_executable".to_string(), TypedValue::Boolean(is_executable));
+        }
+        None => {
+            for column in ["mode", "perms", "uid", "gid", "owner", "group", "inode", "nlink", "accessed", "created", "changed"] {
+                row.insert(column.to_string(), TypedValue::Null);
+            }
+            row.insert("is_executable".to_string(), TypedValue::Boolean(false));
+        }
+    }
+    row
+}
+
+/// Rows of a directory table read from the persistent index (see `INDEX ADD`),
+/// or None when its root is not indexed and has to be walked.
+fn indexed_rows(files: &FileSource) -> Option<Vec<Row>> {
+    if !files.use_index || files.follow_links || !files.root.is_dir() {
+        return None;
+    }
+    // The same pruning as the walk in `scan`, so only what it would read gets re-checked.
+    let prune = |path: &Path, entry: &crate::indexer::IndexedEntry| !entry.is_walked_dir() || files.pushdown.may_contain(path, &files.root);
+    let entries = crate::indexer::indexed_entries(&files.root, files.max_depth(), prune)?;
+    let mut digests = Vec::new();
+    let mut rows = Vec::new();
+    for (relative, entry) in entries {
+        let depth = relative.split('/').count();
+        if let (Some(digest), Some(modified)) = (&entry.sha256, entry.modified) {
+            for column in ["hash", "sha256"] {
+                digests.push(((column.to_string(), entry.inode, entry.size, modified), digest.clone()));
+            }
+        }
+        rows.push(entry_row(&files.root.join(&relative), &files.root, depth, entry.is_symlink, Some(&entry)));
+    }
+    // Hashes stored in the index spare `hash` and `sha256` from reading the files.
+    if let Ok(mut cache) = HASH_CACHE.lock() {
+        cache.extend(digests);
+    }
+    Some(rows)
+}
+
+/// Renders permission bits the way `ls -l` does, e.g. `rwxr-xr-x`.
+fn perms_string(mode: u32) -> String {
+    let mut perms = String::with_capacity(9);
+    for (shift, special, set_char) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = (mode >> shift) & 7;
+        perms.push(if bits & 4 != 0 { 'r' } else { '-' });
+        perms.push(if bits & 2 != 0 { 'w' } else { '-' });
+        perms.push(match (bits & 1 != 0, mode & special != 0) {
+            (true, true) => set_char,
+            (false, true) => set_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    perms
+}
+
+/// Projects one result row and computes its ORDER BY keys in the same scope,
+/// so aggregate sort keys such as `ORDER BY SUM(size)` see the whole group.
+fn output_row(
+    select: &Select,
+    order_by: &[sqlparser::ast::OrderByExpr],
+    columns: &[String],
+    scope: &Scope,
+) -> Result<(Vec<TypedValue>, Projected), String> {
+    let projected = project_row(&select.projection, columns, scope)?;
+    let mut keys = Vec::with_capacity(order_by.len());
+    for order in order_by {
+        keys.push(order_key(&order.expr, scope, &projected)?);
+    }
+    Ok((keys, projected))
+}
+
+/// Builds an output row from the SELECT list. Plain columns keep their name,
+/// aliased items use the alias and other expressions are named after their SQL text.
+fn project_row(projection: &[SelectItem], columns: &[String], scope: &Scope) -> Result<Projected, String> {
+    let mut out = Projected::new();
+    for item in projection {
+        match item {
+            SelectItem::Wildcard(_) => {
+                for col in columns {
+                    if let Some(val) = scope.row.get(col) {
+                        out.push((col.clone(), val.clone()));
+                    }
+                }
+            }
+            // `a.*` in a join: every column of one table.
+            SelectItem::QualifiedWildcard(SelectItemQualifiedWildcardKind::ObjectName(table), _) => {
+                let prefix = format!("{}.", table.to_string().to_lowercase());
+                let before = out.len();
+                for col in columns.iter().filter(|c| c.starts_with(&prefix)) {
+                    if let Some(val) = scope.row.get(col) {
+                        out.push((col.clone(), val.clone()));
+                    }
+                }
+                if out.len() == before {
+                    return Err(format!("Unknown table in '{}'", item));
+                }
+            }
+            SelectItem::UnnamedExpr(expr) => {
+                let label = match expr {
+                    Expr::Identifier(ident) => ident.value.to_lowercase(),
+                    other => other.to_string(),
+                };
+                out.push((label, eval_value(expr, scope)?));
+            }
+            SelectItem::ExprWithAlias { expr, alias } => {
+                out.push((alias.value.clone(), eval_value(expr, scope)?));
+            }
+            _ => {
+                return Err(format!("Unsupported select item '{}'", item));
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn order_exprs(order_by: &OrderBy) -> Result<&[sqlparser::ast::OrderByExpr], String> {
+    match &order_by.kind {
+        OrderByKind::Expressions(exprs) => Ok(exprs),
+        OrderByKind::All(_) => Err("ORDER BY ALL is not supported".to_string()),
+    }
+}
+
+/// Resolves one ORDER BY term for a row. A number refers to a SELECT item by
+/// position, a name matching a SELECT item (or alias) uses the projected value,
+/// and anything else is evaluated against the source row.
+fn order_key(expr: &Expr, scope: &Scope, projected: &Projected) -> Result<TypedValue, String> {
+    match expr {
+        Expr::Value(val) => {
+            if let Value::Number(n, _) = &**val {
+                let position = n
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|p| *p >= 1 && *p <= projected.len())
+                    .ok_or_else(|| format!("ORDER BY position {} is not in the select list", n))?;
+                return Ok(projected[position - 1].1.clone());
+            }
+            eval_value(expr, scope)
+        }
+        Expr::Identifier(ident) => {
+            match projected.iter().find(|(col, _)| col.eq_ignore_ascii_case(&ident.value)) {
+                Some((_, val)) => Ok(val.clone()),
+                None => eval_value(expr, scope),
+            }
+        }
+        _ => eval_value(expr, scope),
+    }
+}
+
+fn sort_results(results: &mut [(Vec<TypedValue>, Projected)], order_by: &[sqlparser::ast::OrderByExpr]) {
+    results.sort_by(|(a, _), (b, _)| {
+        for (i, order) in order_by.iter().enumerate() {
+            let asc = order.options.asc.unwrap_or(true);
+            let ord = compare_for_sort(&a[i], &b[i], asc, order.options.nulls_first);
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+        Ordering::Equal
+    });
+}
+
+/// Orders two values by type (numbers numerically, text lexically). NULLs sort
+/// last ascending and first descending unless NULLS FIRST/LAST says otherwise.
+fn compare_for_sort(a: &TypedValue, b: &TypedValue, asc: bool, nulls_first: Option<bool>) -> Ordering {
+    let nulls_first = nulls_first.unwrap_or(!asc);
+    match (a, b) {
+        (TypedValue::Null, TypedValue::Null) => Ordering::Equal,
+        (TypedValue::Null, _) => if nulls_first { Ordering::Less } else { Ordering::Greater },
+        (_, TypedValue::Null) => if nulls_first { Ordering::Greater } else { Ordering::Less },
+        _ => {
+            let ord = a.partial_cmp(b).unwrap_or(Ordering::Equal);
+            if asc { ord } else { ord.reverse() }
+        }
+    }
+}
+
+fn limit_and_offset(query: &Query) -> Result<(usize, Option<usize>), String> {
+    let (limit, offset) = match &query.limit_clause {
+        None => return Ok((0, None)),
+        Some(LimitClause::LimitOffset { limit, offset, .. }) => {
+            (limit.as_ref(), offset.as_ref().map(|o| &o.value))
+        }
+        Some(LimitClause::OffsetCommaLimit { offset, limit }) => (Some(limit), Some(offset)),
+    };
+
+    let offset = match offset {
+        Some(expr) => row_count(expr, "OFFSET")?,
+        None => 0,
+    };
+    let limit = match limit {
+        Some(expr) => Some(row_count(expr, "LIMIT")?),
+        None => None,
+    };
+    Ok((offset, limit))
+}
+
+fn row_count(expr: &Expr, clause: &str) -> Result<usize, String> {
+    match eval_value(expr, &Scope { row: &Row::new(), group: None })? {
+        TypedValue::Number(n) if n >= 0.0 && n.fract() == 0.0 => Ok(n as usize),
+        _ => Err(format!("{} expects a non-negative whole number, got '{}'", clause, expr)),
+    }
+}
+
+const AGGREGATE_FUNCTIONS: &[&str] = &["COUNT", "SUM", "AVG", "MIN", "MAX"];
+
+fn is_aggregate_function(func: &Function) -> bool {
+    let name = func.name.to_string().to_uppercase();
+    AGGREGATE_FUNCTIONS.contains(&name.as_str())
+}
+
+fn is_aggregate_query(select: &Select) -> bool {
+    let grouped = match &select.group_by {
+        GroupByExpr::Expressions(exprs, _) => !exprs.is_empty(),
+        GroupByExpr::All(_) => true,
+    };
+    grouped
+        || select.having.is_some()
+        || select.projection.iter().any(|item| match item {
+            SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => contains_aggregate(expr),
+            _ => false,
+        })
+}
+
+/// Whether an aggregate call appears anywhere inside `expr`.
+fn contains_aggregate(expr: &Expr) -> bool {
+    match expr {
+        Expr::Function(func) => {
+            is_aggregate_function(func) || function_args(func).iter().any(|arg| matches!(arg, Some(e) if contains_aggregate(e)))
+        }
+        Expr::BinaryOp { left, right, .. } => contains_aggregate(left) || contains_aggregate(right),
+        Expr::UnaryOp { expr, .. } | Expr::Nested(expr) => contains_aggregate(expr),
+        Expr::Like { expr, pattern, .. } | Expr::ILike { expr, pattern, .. } | Expr::RLike { expr, pattern, .. } => {
+            contains_aggregate(expr) || contains_aggregate(pattern)
+        }
+        Expr::InList { expr, list, .. } => contains_aggregate(expr) || list.iter().any(contains_aggregate),
+        Expr::Substring { expr, substring_from, substring_for, .. } => {
+            contains_aggregate(expr) || [substring_from, substring_for].into_iter().flatten().any(|e| contains_aggregate(e))
+        }
+        Expr::Between { expr, low, high, .. } => contains_aggregate(expr) || contains_aggregate(low) || contains_aggregate(high),
+        Expr::IsNull(expr)
+        | Expr::IsNotNull(expr)
+        | Expr::IsTrue(expr)
+        | Expr::IsNotTrue(expr)
+        | Expr::IsFalse(expr)
+        | Expr::IsNotFalse(expr) => contains_aggregate(expr),
+        _ => false,
+    }
+}
+
+/// The positional arguments of a function call; `None` stands for `*`.
+fn function_args(func: &Function) -> Vec<Option<&Expr>> {
+    match &func.args {
+        FunctionArguments::List(list) => list
+            .args
+            .iter()
+            .map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(e)) => Some(e),
+                FunctionArg::Named { arg: FunctionArgExpr::Expr(e), .. } => Some(e),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Splits the matched rows into GROUP BY groups, keeping first-seen order. Each
+/// group is returned with a representative row used for non-aggregated columns.
+/// Without GROUP BY the whole result is a single group, even when it is empty.
+fn group_rows(select: &Select, rows: Vec<Row>, probe: &Row) -> Result<Vec<(Row, Vec<Row>)>, String> {
+    let exprs: Vec<&Expr> = match &select.group_by {
+        GroupByExpr::Expressions(exprs, _) => exprs.iter().map(|e| group_expr(e, &select.projection)).collect::<Result<_, _>>()?,
+        GroupByExpr::All(_) => return Err("GROUP BY ALL is not supported".to_string()),
+    };
+
+    if exprs.is_empty() {
+        let representative = rows.first().cloned().unwrap_or_else(|| probe.clone());
+        return Ok(vec![(representative, rows)]);
+    }
+
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut groups: Vec<(Row, Vec<Row>)> = Vec::new();
+    for row in rows {
+        let mut key = Vec::with_capacity(exprs.len());
+        for expr in &exprs {
+            key.push(eval_value(expr, &Scope { row: &row, group: None })?);
+        }
+        let key = format!("{:?}", key);
+        match positions.get(&key) {
+            Some(&i) => groups[i].1.push(row),
+            None => {
+                positions.insert(key, groups.len());
+                groups.push((row.clone(), vec![row]));
+            }
+        }
+    }
+    Ok(groups)
+}
+
+/// GROUP BY may name a SELECT item by position or alias; resolve those to the
+/// underlying expression.
+fn group_expr<'a>(expr: &'a Expr, projection: &'a [SelectItem]) -> Result<&'a Expr, String> {
+    match expr {
+        Expr::Value(val) => {
+            if let Value::Number(n, _) = &**val {
+                let item = n
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|p| p.checked_sub(1))
+                    .and_then(|i| projection.get(i))
+                    .ok_or_else(|| format!("GROUP BY position {} is not in the select list", n))?;
+                return match item {
+                    SelectItem::UnnamedExpr(e) | SelectItem::ExprWithAlias { expr: e, .. } => Ok(e),
+                    _ => Err(format!("GROUP BY position {} refers to '{}'", n, item)),
+                };
+            }
+            Ok(expr)
+        }
+        Expr::Identifier(ident) if !is_column(&ident.value.to_lowercase()) => {
+            let aliased = projection.iter().find_map(|item| match item {
+                SelectItem::ExprWithAlias { expr, alias } if alias.value.eq_ignore_ascii_case(&ident.value) => Some(expr),
+                _ => None,
+            });
+            Ok(aliased.unwrap_or(expr))
+        }
+        _ => Ok(expr),
+    }
+}
+
+fn eval_aggregate(func: &Function, scope: &Scope) -> Result<TypedValue, String> {
+    let name = func.name.to_string().to_uppercase();
+    let group = scope
+        .group
+        .ok_or_else(|| format!("Aggregate function {} is not allowed here", name))?;
+
+    let args = function_args(func);
+    if args.len() != 1 {
+        return Err(format!("{} expects exactly one argument", name));
+    }
+    if args[0].is_none() && name != "COUNT" {
+        return Err(format!("{}(*) is not supported; pass a column", name));
+    }
+
+    let mut values = Vec::new();
+    for row in group {
+        let value = match args[0] {
+            Some(arg) => eval_value(arg, &Scope { row, group: None })?,
+            None => TypedValue::Boolean(true),
+        };
+        if value != TypedValue::Null {
+            values.push(value);
+        }
+    }
+
+    let distinct = matches!(&func.args, FunctionArguments::List(list) if list.duplicate_treatment == Some(DuplicateTreatment::Distinct));
+    if distinct {
+        let mut seen = std::collections::HashSet::new();
+        values.retain(|v| seen.insert(format!("{:?}", v)));
+    }
+
+    match name.as_str() {
+        "COUNT" => Ok(TypedValue::Number(values.len() as f64)),
+        "MIN" => Ok(values.into_iter().reduce(|a, b| if b < a { b } else { a }).unwrap_or(TypedValue::Null)),
+        "MAX" => Ok(values.into_iter().reduce(|a, b| if b > a { b } else { a }).unwrap_or(TypedValue::Null)),
+        "SUM" | "AVG" => {
+            if values.is_empty() {
+                return Ok(TypedValue::Null);
+            }
+            let mut total = 0.0;
+            for value in &values {
+                total += match value {
+                    TypedValue::Number(n) => *n,
+                    TypedValue::Text(s) => s.parse::<f64>().map_err(|_| format!("{} expects numeric values, got '{}'", name, s))?,
+                    TypedValue::Boolean(b) => if *b { 1.0 } else { 0.0 },
+                    TypedValue::Null => 0.0,
+                    other => return Err(format!("{} expects numeric values, got {}", name, other.describe())),
+                };
+            }
+            if name == "AVG" {
+                total /= values.len() as f64;
+            }
+            Ok(TypedValue::Number(total))
+        }
+        _ => Err(format!("Unsupported aggregate function {}", name)),
+    }
+}
+
+fn evaluate_expr(expr: &Expr, scope: &Scope) -> Result<bool, String> {
+    Ok(truth(expr, scope)? == Some(true))
+}
+
+/// SQL's three-valued logic: `None` is UNKNOWN, what comparisons with NULL give.
+/// NOT UNKNOWN stays UNKNOWN, and AND/OR only settle it when the other side
+/// decides (`FALSE AND x`, `TRUE OR x`), so `NOT ext IN ('csv')` skips NULL
+/// extensions just like `ext NOT IN ('csv')` does.
+fn truth(expr: &Expr, scope: &Scope) -> Result<Option<bool>, String> {
+    let ordering = |left, right| compare(left, right, scope);
+    match expr {
+        Expr::BinaryOp { left, op, right } => {
+            match op {
+                BinaryOperator::And => match truth(left, scope)? {
+                    Some(false) => Ok(Some(false)),
+                    l => Ok(match (l, truth(right, scope)?) {
+                        (_, Some(false)) => Some(false),
+                        (Some(true), Some(true)) => Some(true),
+                        _ => None,
+                    }),
+                },
+                BinaryOperator::Or => match truth(left, scope)? {
+                    Some(true) => Ok(Some(true)),
+                    l => Ok(match (l, truth(right, scope)?) {
+                        (_, Some(true)) => Some(true),
+                        (Some(false), Some(false)) => Some(false),
+                        _ => None,
+                    }),
+                },
+                BinaryOperator::Eq => Ok(ordering(left, right)?.map(|o| o == Ordering::Equal)),
+                BinaryOperator::NotEq => Ok(ordering(left, right)?.map(|o| o != Ordering::Equal)),
+                BinaryOperator::Gt => Ok(ordering(left, right)?.map(|o| o == Ordering::Greater)),
+                BinaryOperator::Lt => Ok(ordering(left, right)?.map(|o| o == Ordering::Less)),
+                BinaryOperator::GtEq => Ok(ordering(left, right)?.map(|o| o != Ordering::Less)),
+                BinaryOperator::LtEq => Ok(ordering(left, right)?.map(|o| o != Ordering::Greater)),
+                BinaryOperator::Plus
+                | BinaryOperator::Minus
+                | BinaryOperator::Multiply
+                | BinaryOperator::Divide
+                | BinaryOperator::Modulo
+                | BinaryOperator::StringConcat => {
+                    Ok(value_truth(eval_value(expr, scope)?))
+                },
+                BinaryOperator::PGCustomBinaryOperator(name) if name.len() == 1 && name[0].eq_ignore_ascii_case("glob") => {
+                    matches_pattern(left, right, scope, PatternKind::Glob)
+                },
+                _ => Err(format!("Unsupported operator '{}' in '{}'", op, expr)),
+            }
+        }
+        Expr::Like { negated, any: false, expr: target, pattern, escape_char } => {
+            let kind = PatternKind::Like { case_insensitive: false, escape: escape_char_of(escape_char)? };
+            Ok(matches_pattern(target, pattern, scope, kind)?.map(|m| m != *negated))
+        }
+        Expr::ILike { negated, any: false, expr: target, pattern, escape_char } => {
+            let kind = PatternKind::Like { case_insensitive: true, escape: escape_char_of(escape_char)? };
+            Ok(matches_pattern(target, pattern, scope, kind)?.map(|m| m != *negated))
+        }
+        Expr::RLike { negated, expr: target, pattern, .. } => {
+            Ok(matches_pattern(target, pattern, scope, PatternKind::Regex)?.map(|m| m != *negated))
+        }
+        Expr::InList { expr: target, list, negated } => {
+            let value = eval_value(target, scope)?;
+            if value == TypedValue::Null {
+                return Ok(None);
+            }
+            // Not found among a list holding a NULL is UNKNOWN, not FALSE.
+            let mut found = Some(false);
+            for item in list {
+                let (l, r) = coerce(value.clone(), eval_value(item, scope)?);
+                if r == TypedValue::Null {
+                    found = None;
+                } else if l == r {
+                    found = Some(true);
+                    break;
+                }
+            }
+            Ok(found.map(|f| f != *negated))
+        }
+        Expr::Between { expr: target, negated, low, high } => {
+            let (Some(above_low), Some(below_high)) = (ordering(target, low)?, ordering(target, high)?) else {
+                return Ok(None);
+            };
+            let inside = above_low != Ordering::Less && below_high != Ordering::Greater;
+            Ok(Some(inside != *negated))
+        }
+        Expr::IsNull(target) => Ok(Some(eval_value(target, scope)? == TypedValue::Null)),
+        Expr::IsNotNull(target) => Ok(Some(eval_value(target, scope)? != TypedValue::Null)),
+        Expr::IsTrue(target) => Ok(Some(truth(target, scope)? == Some(true))),
+        Expr::IsNotTrue(target) => Ok(Some(truth(target, scope)? != Some(true))),
+        Expr::IsFalse(target) => Ok(Some(truth(target, scope)? == Some(false))),
+        Expr::IsNotFalse(target) => Ok(Some(truth(target, scope)? != Some(false))),
+        Expr::UnaryOp { op: UnaryOperator::Not, expr: inner } => Ok(truth(inner, scope)?.map(|b| !b)),
+        Expr::Nested(nested) => truth(nested, scope),
+        other => Ok(value_truth(eval_value(other, scope)?)),
+    }
+}
+
+/// A value used as a condition: NULL is UNKNOWN, and anything but a boolean is false.
+fn value_truth(value: TypedValue) -> Option<bool> {
+    match value {
+        TypedValue::Boolean(b) => Some(b),
+        TypedValue::Null => None,
+        _ => Some(false),
+    }
+}
+
+enum PatternKind {
+    /// SQL LIKE: `%` matches any run of characters, `_` a single one.
+    Like { case_insensitive: bool, escape: Option<char> },
+    /// Shell-style GLOB: `*`, `?` and `[...]` character classes, case-sensitive.
+    Glob,
+    /// REGEXP / RLIKE: a regular expression that may match anywhere in the value.
+    Regex,
+}
+
+lazy_static! {
+    /// Compiled patterns keyed by their regex source, so a pattern is compiled
+    /// once per query instead of once per row. Emptied when a query starts.
+    static ref PATTERN_CACHE: Mutex<HashMap<String, Regex>> = Mutex::new(HashMap::new());
+}
+
+fn clear_pattern_cache() {
+    if let Ok(mut cache) = PATTERN_CACHE.lock() {
+        cache.clear();
+    }
+}
+
+fn escape_char_of(escape_char: &Option<sqlparser::ast::ValueWithSpan>) -> Result<Option<char>, String> {
+    match escape_char.as_ref().map(|v| &v.value) {
+        None => Ok(None),
+        Some(Value::SingleQuotedString(s)) if s.chars().count() == 1 => Ok(s.chars().next()),
+        Some(other) => Err(format!("ESCAPE expects a single character, got {}", other)),
+    }
+}
 
-            let modified = if let Some(m) = &metadata {
-                if let Ok(sys_time) = m.modified() {
-                    let datetime: DateTime<Local> = sys_time.into();
-                    datetime.format("%Y-%m-%d %H:%M:%S").to_string()
-                } else {
-                    "-".to_string()
-                }
-            } else {
-                "-".to_string()
-            };
+/// Matches the text of `target` against `pattern`. A NULL on either side gives UNKNOWN.
+fn matches_pattern(target: &Expr, pattern: &Expr, scope: &Scope, kind: PatternKind) -> Result<Option<bool>, String> {
+    let (Some(text), Some(pattern)) = (eval_value(target, scope)?.as_text(), eval_value(pattern, scope)?.as_text()) else {
+        return Ok(None);
+    };
 
-            // Implement WHERE clause filtering
-            if let Some(selection) = &select.selection {
-                if !evaluate_expr(selection, &name, &ext, size, is_dir) {
-                    continue;
-                }
+    let source = pattern_regex(pattern, kind);
+    // Clones share the compiled program, so walker threads match without holding the lock.
+    let regex = {
+        let mut cache = PATTERN_CACHE.lock().map_err(|_| "Pattern cache is unavailable".to_string())?;
+        match cache.get(&source) {
+            Some(regex) => regex.clone(),
+            None => {
+                let compiled = Regex::new(&source).map_err(|e| format!("Invalid pattern '{}': {}", source, e))?;
+                cache.insert(source, compiled.clone());
+                compiled
             }
-
-            table.add_row(vec![
-                Cell::new(name).fg(if is_dir { CColor::Green } else { CColor::White }),
-                Cell::new(ext),
-                Cell::new(size.to_string()),
-                Cell::new(modified),
-                Cell::new(is_dir.to_string()),
-            ]);
         }
-
-        crate::cprintln!("\n{}", table);
-        Ok(format!("Executed SQL query"))
-    } else {
-        Err("Unsupported query type".to_string())
-    }
+    };
+    Ok(Some(regex.is_match(&text)))
 }
 
-fn evaluate_expr(expr: &Expr, name: &str, ext: &str, size: u64, is_dir: bool) -> bool {
-    match expr {
-        Expr::BinaryOp { left, op, right } => {
-            match op {
-                BinaryOperator::And => evaluate_expr(left, name, ext, size, is_dir) && evaluate_expr(right, name, ext, size, is_dir),
-                BinaryOperator::Or => evaluate_expr(left, name, ext, size, is_dir) || evaluate_expr(right, name, ext, size, is_dir),
-                BinaryOperator::Eq => {
-                    let (l_val, r_val) = get_values(left, right, name, ext, size, is_dir);
-                    l_val == r_val
-                },
-                BinaryOperator::NotEq => {
-                    let (l_val, r_val) = get_values(left, right, name, ext, size, is_dir);
-                    l_val != r_val
-                },
-                BinaryOperator::Gt => {
-                    let (l_val, r_val) = get_values(left, right, name, ext, size, is_dir);
-                    l_val > r_val
-                },
-                BinaryOperator::Lt => {
-                    let (l_val, r_val) = get_values(left, right, name, ext, size, is_dir);
-                    l_val < r_val
-                },
-                BinaryOperator::GtEq => {
-                    let (l_val, r_val) = get_values(left, right, name, ext, size, is_dir);
-                    l_val >= r_val
-                },
-                BinaryOperator::LtEq => {
-                    let (l_val, r_val) = get_values(left, right, name, ext, size, is_dir);
-                    l_val <= r_val
-                },
-                _ => false,
+/// The regular expression a pattern stands for. LIKE and GLOB patterns must match the whole value.
+fn pattern_regex(pattern: String, kind: PatternKind) -> String {
+    match kind {
+        PatternKind::Like { case_insensitive, escape } => {
+            let mut re = String::from(if case_insensitive { "(?is)^" } else { "(?s)^" });
+            let mut chars = pattern.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    c if Some(c) == escape => {
+                        if let Some(next) = chars.next() {
+                            re.push_str(&regex::escape(&next.to_string()));
+                        }
+                    }
+                    '%' => re.push_str(".*"),
+                    '_' => re.push('.'),
+                    c => re.push_str(&regex::escape(&c.to_string())),
+                }
             }
+            re.push('$');
+            re
         }
-        Expr::Nested(nested) => evaluate_expr(nested, name, ext, size, is_dir),
-        _ => false,
+        PatternKind::Glob => {
+            let mut re = String::from("(?s)^");
+            let mut chars = pattern.chars().peekable();
+            while let Some(c) = chars.next() {
+                match c {
+                    '*' => re.push_str(".*"),
+                    '?' => re.push('.'),
+                    '[' => {
+                        re.push('[');
+                        if chars.peek() == Some(&'^') || chars.peek() == Some(&'!') {
+                            chars.next();
+                            re.push('^');
+                        }
+                        for c in chars.by_ref() {
+                            if c == ']' {
+                                break;
+                            }
+                            if c == '\\' || c == '[' {
+                                re.push('\\');
+                            }
+                            re.push(c);
+                        }
+                        re.push(']');
+                    }
+                    c => re.push_str(&regex::escape(&c.to_string())),
+                }
+            }
+            re.push('$');
+            re
+        }
+        PatternKind::Regex => pattern,
     }
 }
 
 // A helper to resolve values for comparison. For simplicity, we convert everything to Strings,
 // except if both look like numbers, we could convert to f64. To handle `size > 100`, we need numeric comparison.
-#[derive(PartialEq, PartialOrd)]
-enum TypedValue {
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub(crate) enum TypedValue {
     Number(f64),
     Text(String),
     Boolean(bool),
+    Timestamp(DateTime<Local>),
+    Interval(TimeDelta),
     Null,
 }
 
-fn get_values(left: &Expr, right: &Expr, name: &str, ext: &str, size: u64, is_dir: bool) -> (TypedValue, TypedValue) {
-    let l = eval_value(left, name, ext, size, is_dir);
-    let r = eval_value(right, name, ext, size, is_dir);
-    
+impl TypedValue {
+    /// Converts to the pipeline `Value` type. Whole numbers become Integer cells.
+    fn to_cell(&self) -> CellValue {
+        match self {
+            TypedValue::Number(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => CellValue::Integer(*n as i64),
+            TypedValue::Number(n) => CellValue::Float(*n),
+            TypedValue::Text(s) => CellValue::String(s.clone()),
+            TypedValue::Boolean(b) => CellValue::Boolean(*b),
+            TypedValue::Timestamp(_) | TypedValue::Interval(_) => CellValue::String(self.as_text().unwrap_or_default()),
+            TypedValue::Null => CellValue::Null,
+        }
+    }
+
+    /// The type name used in error messages.
+    fn describe(&self) -> &'static str {
+        match self {
+            TypedValue::Number(_) => "number",
+            TypedValue::Text(_) => "text",
+            TypedValue::Boolean(_) => "boolean",
+            TypedValue::Timestamp(_) => "timestamp",
+            TypedValue::Interval(_) => "interval",
+            TypedValue::Null => "null",
+        }
+    }
+
+    /// The value as text for string operators; NULL has no text.
+    fn as_text(&self) -> Option<String> {
+        match self {
+            TypedValue::Text(s) => Some(s.clone()),
+            TypedValue::Number(n) if n.fract() == 0.0 => Some(format!("{}", *n as i64)),
+            TypedValue::Number(n) => Some(n.to_string()),
+            TypedValue::Boolean(b) => Some(b.to_string()),
+            TypedValue::Timestamp(t) => Some(t.format("%Y-%m-%d %H:%M:%S").to_string()),
+            TypedValue::Interval(d) => Some(format_interval(*d)),
+            TypedValue::Null => None,
+        }
+    }
+}
+
+/// Compares two expressions after coercion. `None` means the result is unknown because
+/// one side is NULL (or the values cannot be ordered), which no comparison accepts.
+fn compare(left: &Expr, right: &Expr, scope: &Scope) -> Result<Option<Ordering>, String> {
+    let (l, r) = coerce(eval_value(left, scope)?, eval_value(right, scope)?);
+    match (&l, &r) {
+        (TypedValue::Null, _) | (_, TypedValue::Null) => Ok(None),
+        (TypedValue::Timestamp(_), TypedValue::Text(s)) | (TypedValue::Text(s), TypedValue::Timestamp(_)) => {
+            Err(format!("Cannot compare a timestamp with '{}'. Use a date such as '2026-01-01' or '2026-01-01 12:00'", s))
+        }
+        (TypedValue::Interval(_), TypedValue::Text(s)) | (TypedValue::Text(s), TypedValue::Interval(_)) => {
+            Err(format!("Cannot compare an interval with '{}'. Use an interval such as '7 days'", s))
+        }
+        _ => Ok(l.partial_cmp(&r)),
+    }
+}
+
+fn coerce(l: TypedValue, r: TypedValue) -> (TypedValue, TypedValue) {
     // If one is Number and the other is Text that parses to Number, cast it.
     match (&l, &r) {
         (TypedValue::Number(_), TypedValue::Text(s)) => {
@@ -169,26 +2908,42 @@ fn get_values(left: &Expr, right: &Expr, name: &str, ext: &str, size: u64, is_di
                 return (TypedValue::Number(n), r);
             }
         }
+        // Dates and intervals are written as strings: `modified >= '2026-01-01'`.
+        (TypedValue::Timestamp(_), TypedValue::Text(s)) => {
+            if let Some(t) = parse_timestamp(s) {
+                return (l, TypedValue::Timestamp(t));
+            }
+        }
+        (TypedValue::Text(s), TypedValue::Timestamp(_)) => {
+            if let Some(t) = parse_timestamp(s) {
+                return (TypedValue::Timestamp(t), r);
+            }
+        }
+        (TypedValue::Interval(_), TypedValue::Text(s)) => {
+            if let Some(d) = parse_interval(s) {
+                return (l, TypedValue::Interval(d));
+            }
+        }
+        (TypedValue::Text(s), TypedValue::Interval(_)) => {
+            if let Some(d) = parse_interval(s) {
+                return (TypedValue::Interval(d), r);
+            }
+        }
         _ => {}
     }
     
     (l, r)
 }
 
-fn eval_value(expr: &Expr, name: &str, ext: &str, size: u64, is_dir: bool) -> TypedValue {
+fn eval_value(expr: &Expr, scope: &Scope) -> Result<TypedValue, String> {
     match expr {
-        Expr::Identifier(ident) => {
-            let col = ident.value.to_lowercase();
-            match col.as_str() {
-                "name" => TypedValue::Text(name.to_string()),
-                "ext" => TypedValue::Text(ext.to_string()),
-                "size" => TypedValue::Number(size as f64),
-                "is_dir" => TypedValue::Boolean(is_dir),
-                _ => TypedValue::Null,
-            }
-        }
+        Expr::Identifier(ident) => column_value(None, &ident.value, scope),
+        Expr::CompoundIdentifier(parts) => match parts.as_slice() {
+            [table, column] => column_value(Some(&table.value), &column.value, scope),
+            _ => Err(format!("Unknown column '{}'", expr)),
+        },
         Expr::Value(val) => {
-            match &**val {
+            Ok(match &**val {
                 Value::Number(n, _) => {
                     if let Ok(num) = n.parse::<f64>() {
                         TypedValue::Number(num)
@@ -199,8 +2954,852 @@ fn eval_value(expr: &Expr, name: &str, ext: &str, size: u64, is_dir: bool) -> Ty
                 Value::SingleQuotedString(s) | Value::DoubleQuotedString(s) => TypedValue::Text(s.clone()),
                 Value::Boolean(b) => TypedValue::Boolean(*b),
                 _ => TypedValue::Null,
+            })
+        }
+        Expr::Nested(nested) => eval_value(nested, scope),
+        Expr::BinaryOp {
+            left,
+            op: op @ (BinaryOperator::Plus
+            | BinaryOperator::Minus
+            | BinaryOperator::Multiply
+            | BinaryOperator::Divide
+            | BinaryOperator::Modulo),
+            right,
+        } => arithmetic(eval_value(left, scope)?, op, eval_value(right, scope)?),
+        Expr::BinaryOp { left, op: BinaryOperator::StringConcat, right } => {
+            match (eval_value(left, scope)?.as_text(), eval_value(right, scope)?.as_text()) {
+                (Some(l), Some(r)) => Ok(TypedValue::Text(l + &r)),
+                _ => Ok(TypedValue::Null),
             }
         }
-        _ => TypedValue::Null,
+        Expr::UnaryOp { op: op @ (UnaryOperator::Minus | UnaryOperator::Plus), expr: inner } => {
+            let value = eval_value(inner, scope)?;
+            match (op, value) {
+                (UnaryOperator::Plus, value) => Ok(value),
+                (_, TypedValue::Number(n)) => Ok(TypedValue::Number(-n)),
+                (_, TypedValue::Interval(d)) => Ok(TypedValue::Interval(-d)),
+                (_, TypedValue::Null) => Ok(TypedValue::Null),
+                (_, other) => Err(format!("Cannot negate {}", other.describe())),
+            }
+        }
+        Expr::Substring { expr: inner, substring_from, substring_for, .. } => {
+            let mut args = vec![eval_value(inner, scope)?];
+            for arg in [substring_from, substring_for].into_iter().flatten() {
+                args.push(eval_value(arg, scope)?);
+            }
+            substr(&args)
+        }
+        // `TIMESTAMP '2026-01-01 12:00'`, `DATE '2026-01-01'`.
+        Expr::TypedString(typed) if matches!(typed.data_type, DataType::Timestamp(..) | DataType::Datetime(_) | DataType::Date) => {
+            let text = typed.value.clone().into_string().unwrap_or_default();
+            parse_timestamp(&text)
+                .map(TypedValue::Timestamp)
+                .ok_or_else(|| format!("Invalid timestamp '{}'. Use e.g. '2026-01-01' or '2026-01-01 13:45'", text))
+        }
+        Expr::Interval(interval) => {
+            let value = eval_value(&interval.value, scope)?.as_text().unwrap_or_default();
+            let text = match &interval.leading_field {
+                Some(field) => format!("{} {}", value, field),
+                None => value,
+            };
+            parse_interval(&text)
+                .map(TypedValue::Interval)
+                .ok_or_else(|| format!("Invalid interval '{}'. Use e.g. INTERVAL '7 days' or INTERVAL '1 hour 30 minutes'", text))
+        }
+        Expr::BinaryOp { .. }
+        | Expr::Like { .. }
+        | Expr::ILike { .. }
+        | Expr::RLike { .. }
+        | Expr::InList { .. }
+        | Expr::Between { .. }
+        | Expr::IsNull(_)
+        | Expr::IsNotNull(_)
+        | Expr::IsTrue(_)
+        | Expr::IsNotTrue(_)
+        | Expr::IsFalse(_)
+        | Expr::IsNotFalse(_)
+        | Expr::UnaryOp { op: UnaryOperator::Not, .. } => {
+            Ok(truth(expr, scope)?.map_or(TypedValue::Null, TypedValue::Boolean))
+        }
+        Expr::Function(func) if is_aggregate_function(func) => eval_aggregate(func, scope),
+        Expr::Function(func) => eval_function(func, scope),
+        _ => Err(format!("Unsupported expression '{}'", expr)),
+    }
+}
+
+lazy_static! {
+    /// Digests keyed by (column, inode, size, mtime), so repeated queries only
+    /// re-read files that changed.
+    static ref HASH_CACHE: Mutex<HashMap<(String, u64, u64, i64), String>> = Mutex::new(HashMap::new());
+}
+
+/// Looks up a column of the current row. Joined rows store `alias.column`; an
+/// unqualified name there matches when exactly one of the tables has it.
+fn column_value(table: Option<&str>, column: &str, scope: &Scope) -> Result<TypedValue, String> {
+    let row = scope.row;
+    let name = match table {
+        Some(table) => format!("{}.{}", table.to_lowercase(), column),
+        None => column.to_string(),
+    };
+    if let Some(value) = row.get(&name.to_lowercase()) {
+        return Ok(value.clone());
+    }
+    // Data files keep their own header spelling, e.g. `Name` or `userId`.
+    if let Some((_, value)) = row.iter().find(|(key, _)| key.eq_ignore_ascii_case(&name)) {
+        return Ok(value.clone());
+    }
+    let ambiguous = || format!("Column '{}' is ambiguous; qualify it with a table name, e.g. a.{}", column, column);
+    if table.is_none() {
+        let mut found = row
+            .iter()
+            .filter(|(key, _)| key.split_once('.').is_some_and(|(_, c)| c.eq_ignore_ascii_case(column)));
+        if let Some((_, value)) = found.next() {
+            return if found.next().is_some() { Err(ambiguous()) } else { Ok(value.clone()) };
+        }
+    }
+
+    // Only directory tables (the rows with an `is_symlink` column) have content columns.
+    let col = column.to_lowercase();
+    if CONTENT_COLUMNS.contains(&col.as_str()) {
+        if row.contains_key("is_symlink") && table.is_none() {
+            return Ok(content_hash(&col, row));
+        }
+        let mut prefixes = row.keys().filter_map(|key| key.strip_suffix("is_symlink")).filter(|prefix| match table {
+            Some(table) => prefix.strip_suffix('.').is_some_and(|p| p.eq_ignore_ascii_case(table)),
+            None => prefix.ends_with('.'),
+        });
+        if let Some(prefix) = prefixes.next() {
+            if prefixes.next().is_some() {
+                return Err(ambiguous());
+            }
+            let files_row: Row = row
+                .iter()
+                .filter_map(|(key, value)| Some((key.strip_prefix(prefix)?.to_string(), value.clone())))
+                .collect();
+            return Ok(content_hash(&col, &files_row));
+        }
+    }
+
+    let available = if row.contains_key("is_symlink") {
+        format!("{}, {}", FILES_COLUMNS.join(", "), CONTENT_COLUMNS.join(", "))
+    } else {
+        let mut columns: Vec<String> = row.keys().cloned().collect();
+        for prefix in row.keys().filter_map(|key| key.strip_suffix("is_symlink")) {
+            columns.extend(CONTENT_COLUMNS.iter().map(|c| format!("{}{}", prefix, c)));
+        }
+        columns.sort();
+        columns.join(", ")
+    };
+    Err(format!("Unknown column '{}'. Available columns: {}", name, available))
+}
+
+/// Hashes a regular file's contents for the `hash`, `sha256` and `blake3` columns.
+/// Directories, symlinks and unreadable files hash to NULL.
+fn content_hash(column: &str, row: &Row) -> TypedValue {
+    let (Some(TypedValue::Text(path)), Some(TypedValue::Boolean(false)), Some(TypedValue::Boolean(false))) =
+        (row.get("path"), row.get("is_dir"), row.get("is_symlink"))
+    else {
+        return TypedValue::Null;
+    };
+    let key = match (row.get("inode"), row.get("size"), row.get("modified")) {
+        (Some(TypedValue::Number(inode)), Some(TypedValue::Number(size)), Some(TypedValue::Timestamp(mtime))) => {
+            mtime.timestamp_nanos_opt().map(|nanos| (column.to_string(), *inode as u64, *size as u64, nanos))
+        }
+        _ => None,
+    };
+    if let Some(key) = &key
+        && let Some(digest) = HASH_CACHE.lock().ok().and_then(|cache| cache.get(key).cloned())
+    {
+        return TypedValue::Text(digest);
+    }
+
+    let digest = (|| {
+        let mut file = std::fs::File::open(path)?;
+        if column == "blake3" {
+            let mut hasher = blake3::Hasher::new();
+            std::io::copy(&mut file, &mut hasher)?;
+            Ok::<String, std::io::Error>(hasher.finalize().to_hex().to_string())
+        } else {
+            let mut hasher = Sha256::new();
+            std::io::copy(&mut file, &mut hasher)?;
+            Ok(format!("{:x}", hasher.finalize()))
+        }
+    })();
+    match digest {
+        Ok(digest) => {
+            if let Some(key) = key
+                && let Ok(mut cache) = HASH_CACHE.lock()
+            {
+                cache.insert(key, digest.clone());
+            }
+            TypedValue::Text(digest)
+        }
+        Err(_) => TypedValue::Null,
+    }
+}
+
+/// Evaluates a scalar (per-row) function call.
+fn eval_function(func: &Function, scope: &Scope) -> Result<TypedValue, String> {
+    let name = func.name.to_string().to_uppercase();
+    let mut args = Vec::new();
+    for arg in function_args(func) {
+        let arg = arg.ok_or_else(|| format!("{}(*) is not supported", name))?;
+        args.push(eval_value(arg, scope)?);
+    }
+    let arity = |expected: &[usize]| {
+        if expected.contains(&args.len()) {
+            Ok(())
+        } else {
+            Err(format!("{} expects {} argument(s), got {}", name, expected.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(" or "), args.len()))
+        }
+    };
+
+    match name.as_str() {
+        "LOWER" | "UPPER" => {
+            arity(&[1])?;
+            Ok(match args[0].as_text() {
+                Some(text) if name == "LOWER" => TypedValue::Text(text.to_lowercase()),
+                Some(text) => TypedValue::Text(text.to_uppercase()),
+                None => TypedValue::Null,
+            })
+        }
+        "LENGTH" | "CHAR_LENGTH" => {
+            arity(&[1])?;
+            Ok(args[0].as_text().map_or(TypedValue::Null, |text| TypedValue::Number(text.chars().count() as f64)))
+        }
+        "SUBSTR" | "SUBSTRING" => {
+            arity(&[2, 3])?;
+            substr(&args)
+        }
+        "REPLACE" => {
+            arity(&[3])?;
+            Ok(match (args[0].as_text(), args[1].as_text(), args[2].as_text()) {
+                (Some(text), Some(from), Some(to)) if !from.is_empty() => TypedValue::Text(text.replace(&from, &to)),
+                (Some(text), Some(_), Some(_)) => TypedValue::Text(text),
+                _ => TypedValue::Null,
+            })
+        }
+        // NULL arguments are skipped, as in PostgreSQL.
+        "CONCAT" => Ok(TypedValue::Text(args.iter().filter_map(TypedValue::as_text).collect())),
+        "STEM" => {
+            arity(&[1])?;
+            Ok(args[0].as_text().map_or(TypedValue::Null, |text| {
+                TypedValue::Text(Path::new(&text).file_stem().unwrap_or_default().to_string_lossy().to_string())
+            }))
+        }
+        "PARENT" => {
+            arity(&[1])?;
+            Ok(args[0].as_text().map_or(TypedValue::Null, |text| match Path::new(&text).parent() {
+                Some(p) if !p.as_os_str().is_empty() => TypedValue::Text(p.to_string_lossy().to_string()),
+                _ => TypedValue::Text(".".to_string()),
+            }))
+        }
+        "HUMAN_SIZE" => {
+            arity(&[1])?;
+            Ok(to_number(&args[0], &name)?.map_or(TypedValue::Null, |n| TypedValue::Text(human_size(n))))
+        }
+        "ROUND" => {
+            arity(&[1, 2])?;
+            let digits = match args.get(1) {
+                Some(arg) => to_number(arg, &name)?,
+                None => Some(0.0),
+            };
+            Ok(match (to_number(&args[0], &name)?, digits) {
+                (Some(n), Some(digits)) => {
+                    let factor = 10f64.powi(digits as i32);
+                    TypedValue::Number((n * factor).round() / factor)
+                }
+                _ => TypedValue::Null,
+            })
+        }
+        "NOW" | "CURRENT_TIMESTAMP" => {
+            arity(&[0])?;
+            Ok(TypedValue::Timestamp(Local::now()))
+        }
+        "CURRENT_DATE" => {
+            arity(&[0])?;
+            Ok(start_of_day(Local::now().date_naive()).map_or(TypedValue::Null, TypedValue::Timestamp))
+        }
+        "DATE" => {
+            arity(&[1])?;
+            Ok(match to_timestamp(&args[0], &name)? {
+                Some(t) => TypedValue::Text(t.format("%Y-%m-%d").to_string()),
+                None => TypedValue::Null,
+            })
+        }
+        // AGE(t) is the time elapsed since t; AGE(a, b) is a - b.
+        "AGE" => {
+            arity(&[1, 2])?;
+            let (end, start) = match args.as_slice() {
+                [start] => (Some(Local::now()), to_timestamp(start, &name)?),
+                [end, start] => (to_timestamp(end, &name)?, to_timestamp(start, &name)?),
+                _ => unreachable!(),
+            };
+            Ok(match (end, start) {
+                (Some(end), Some(start)) => TypedValue::Interval(end - start),
+                _ => TypedValue::Null,
+            })
+        }
+        _ => Err(format!("Unsupported function '{}'", func.name)),
+    }
+}
+
+/// Reads a function argument as a timestamp; text is parsed, NULL stays `None`.
+fn to_timestamp(value: &TypedValue, function: &str) -> Result<Option<DateTime<Local>>, String> {
+    match value {
+        TypedValue::Timestamp(t) => Ok(Some(*t)),
+        TypedValue::Null => Ok(None),
+        TypedValue::Text(s) => parse_timestamp(s)
+            .map(Some)
+            .ok_or_else(|| format!("{} expects a timestamp, got '{}'", function, s)),
+        other => Err(format!("{} expects a timestamp, got {}", function, other.describe())),
+    }
+}
+
+/// `+ - * / %` over numbers, and `+`/`-` over timestamps and intervals
+/// (intervals can also be scaled by a number). NULL in, NULL out.
+fn arithmetic(left: TypedValue, op: &BinaryOperator, right: TypedValue) -> Result<TypedValue, String> {
+    let (left, right) = coerce(left, right);
+    let minus = *op == BinaryOperator::Minus;
+    Ok(match (&left, op, &right) {
+        (TypedValue::Null, _, _) | (_, _, TypedValue::Null) => TypedValue::Null,
+        (TypedValue::Number(_), BinaryOperator::Divide | BinaryOperator::Modulo, TypedValue::Number(b)) if *b == 0.0 => {
+            return Err("Division by zero".to_string());
+        }
+        (TypedValue::Number(a), _, TypedValue::Number(b)) => TypedValue::Number(match op {
+            BinaryOperator::Plus => a + b,
+            BinaryOperator::Minus => a - b,
+            BinaryOperator::Multiply => a * b,
+            BinaryOperator::Divide => a / b,
+            _ => a % b,
+        }),
+        (TypedValue::Timestamp(t), BinaryOperator::Plus | BinaryOperator::Minus, TypedValue::Interval(d)) => {
+            TypedValue::Timestamp(if minus { *t - *d } else { *t + *d })
+        }
+        (TypedValue::Interval(d), BinaryOperator::Plus, TypedValue::Timestamp(t)) => TypedValue::Timestamp(*t + *d),
+        (TypedValue::Timestamp(a), BinaryOperator::Minus, TypedValue::Timestamp(b)) => TypedValue::Interval(*a - *b),
+        (TypedValue::Interval(a), BinaryOperator::Plus | BinaryOperator::Minus, TypedValue::Interval(b)) => {
+            TypedValue::Interval(if minus { *a - *b } else { *a + *b })
+        }
+        (TypedValue::Interval(d), BinaryOperator::Multiply, TypedValue::Number(n))
+        | (TypedValue::Number(n), BinaryOperator::Multiply, TypedValue::Interval(d)) => {
+            TypedValue::Interval(TimeDelta::milliseconds((d.num_milliseconds() as f64 * n).round() as i64))
+        }
+        (TypedValue::Interval(d), BinaryOperator::Divide, TypedValue::Number(n)) if *n != 0.0 => {
+            TypedValue::Interval(TimeDelta::milliseconds((d.num_milliseconds() as f64 / n).round() as i64))
+        }
+        _ => return Err(format!("Cannot apply '{}' to {} and {}", op, left.describe(), right.describe())),
+    })
+}
+
+/// Reads a function argument as a number; numeric text is accepted, NULL stays `None`.
+fn to_number(value: &TypedValue, function: &str) -> Result<Option<f64>, String> {
+    match value {
+        TypedValue::Number(n) => Ok(Some(*n)),
+        TypedValue::Null => Ok(None),
+        TypedValue::Text(s) => s
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("{} expects a number, got '{}'", function, s)),
+        other => Err(format!("{} expects a number, got {}", function, other.describe())),
+    }
+}
+
+/// SUBSTR(text, start [, length]) with a 1-based start, counting characters.
+fn substr(args: &[TypedValue]) -> Result<TypedValue, String> {
+    let (Some(text), Some(start)) = (args[0].as_text(), to_number(&args[1], "SUBSTR")?) else {
+        return Ok(TypedValue::Null);
+    };
+    let length = match args.get(2) {
+        Some(arg) => match to_number(arg, "SUBSTR")? {
+            Some(n) => Some(n.max(0.0) as usize),
+            None => return Ok(TypedValue::Null),
+        },
+        None => None,
+    };
+    // As in SQL, a start before 1 still counts towards the length.
+    let start = start as i64 - 1;
+    let skip = start.max(0) as usize;
+    let take = length.map_or(usize::MAX, |len| (len as i64 + start.min(0)).max(0) as usize);
+    Ok(TypedValue::Text(text.chars().skip(skip).take(take).collect()))
+}
+
+/// Formats a byte count in 1024-based units: `512 B`, `1.5 KB`, `3.2 GB`.
+fn human_size(bytes: f64) -> String {
+    const UNITS: [&str; 6] = ["B", "KB", "MB", "GB", "TB", "PB"];
+    let mut size = bytes;
+    let mut unit = 0;
+    while size.abs() >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", size, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+fn start_of_day(date: NaiveDate) -> Option<DateTime<Local>> {
+    Local.from_local_datetime(&date.and_hms_opt(0, 0, 0)?).earliest()
+}
+
+/// Parses the date formats users type in queries: `2026-01-01`, `2026-01-01 13:45`,
+/// `2026-01-01 13:45:10` (also with a `T`) and RFC 3339.
+fn parse_timestamp(text: &str) -> Option<DateTime<Local>> {
+    let text = text.trim();
+    if let Ok(t) = DateTime::parse_from_rfc3339(text) {
+        return Some(t.with_timezone(&Local));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(text, format) {
+            return Local.from_local_datetime(&naive).earliest();
+        }
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d").ok().and_then(start_of_day)
+}
+
+/// Parses intervals such as `7 days`, `1 hour 30 minutes` or `2w`, exact down to
+/// nanoseconds. Months and years are approximated as 30 and 365 days.
+fn parse_interval(text: &str) -> Option<TimeDelta> {
+    lazy_static! {
+        static ref PART: Regex = Regex::new(r"^\s*(-?\d+(?:\.\d+)?)\s*([a-z]+)").unwrap();
+    }
+    let text = text.trim().to_lowercase();
+    let mut rest = text.as_str();
+    let mut total: i128 = 0;
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.trim().is_empty() {
+        let caps = PART.captures(rest)?;
+        let unit_nanos: i64 = match &caps[2] {
+            "ns" | "nanosecond" | "nanoseconds" => 1,
+            "us" | "microsecond" | "microseconds" => 1_000,
+            "ms" | "millisecond" | "milliseconds" => 1_000_000,
+            "s" | "sec" | "secs" | "second" | "seconds" => 1_000_000_000,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60_000_000_000,
+            "h" | "hr" | "hrs" | "hour" | "hours" => 3_600_000_000_000,
+            "d" | "day" | "days" => 86_400_000_000_000,
+            "w" | "week" | "weeks" => 604_800_000_000_000,
+            "mon" | "mons" | "month" | "months" => 2_592_000_000_000_000,
+            "y" | "yr" | "yrs" | "year" | "years" => 31_536_000_000_000_000,
+            _ => return None,
+        };
+        // Whole amounts are multiplied exactly; fractions go through f64.
+        total += match caps[1].parse::<i64>() {
+            Ok(amount) => amount as i128 * unit_nanos as i128,
+            Err(_) => (caps[1].parse::<f64>().ok()? * unit_nanos as f64).round() as i128,
+        };
+        rest = &rest[caps[0].len()..];
+    }
+    let seconds = i64::try_from(total.div_euclid(1_000_000_000)).ok()?;
+    TimeDelta::try_seconds(seconds)?.checked_add(&TimeDelta::nanoseconds(total.rem_euclid(1_000_000_000) as i64))
+}
+
+/// Formats an interval the way PostgreSQL does, e.g. `3 days 04:05:06`.
+fn format_interval(delta: TimeDelta) -> String {
+    let sign = if delta < TimeDelta::zero() { "-" } else { "" };
+    let total = delta.num_seconds().abs();
+    let (days, secs) = (total / 86_400, total % 86_400);
+    let clock = format!("{:02}:{:02}:{:02}", secs / 3_600, secs % 3_600 / 60, secs % 60);
+    match days {
+        0 => format!("{}{}", sign, clock),
+        1 => format!("{}1 day {}", sign, clock),
+        _ => format!("{}{} days {}", sign, days, clock),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The WHERE clause of a query, parsed the way `execute_sql` parses it.
+    fn where_clause(condition: &str) -> Expr {
+        let sql = rewrite_query(&format!("SELECT * FROM files WHERE {}", condition));
+        let statement = Parser::parse_sql(&GenericDialect {}, &sql).unwrap().remove(0);
+        let Statement::Query(query) = statement else { panic!("not a query: {}", sql) };
+        let SetExpr::Select(select) = *query.body else { panic!("not a SELECT: {}", sql) };
+        select.selection.unwrap()
+    }
+
+    fn pattern_matches(pattern: &str, kind: PatternKind, text: &str) -> bool {
+        Regex::new(&pattern_regex(pattern.to_string(), kind)).unwrap().is_match(text)
+    }
+
+    fn like(escape: Option<char>) -> PatternKind {
+        PatternKind::Like { case_insensitive: false, escape }
+    }
+
+    /// A scratch directory tree, removed when the test ends. Entries ending in
+    /// `/` are directories; the others are files holding "x".
+    struct Fixture(PathBuf);
+
+    impl Fixture {
+        fn new(name: &str, entries: &[&str]) -> Fixture {
+            let root = std::env::temp_dir().join(format!("dir2-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+            for entry in entries {
+                let path = root.join(entry);
+                let dir = if entry.ends_with('/') { path.as_path() } else { path.parent().unwrap() };
+                std::fs::create_dir_all(dir).unwrap();
+                if !entry.ends_with('/') {
+                    std::fs::write(&path, "x").unwrap();
+                }
+            }
+            Fixture(root)
+        }
+
+        fn path(&self, relative: &str) -> PathBuf {
+            self.0.join(relative)
+        }
+
+        /// `sql` with `{root}` replaced by the fixture's directory.
+        fn sql(&self, sql: &str) -> String {
+            sql.replace("{root}", &self.0.to_string_lossy())
+        }
+
+        fn run(&self, sql: &str) -> Result<CellValue, String> {
+            execute_sql_query(&mut FileSystemState::new(), &FavoritesManager::new().unwrap(), &self.sql(sql))
+        }
+
+        fn statement(&self, sql: &str) -> Statement {
+            Parser::parse_sql(&GenericDialect {}, &rewrite_query(&self.sql(sql))).unwrap().remove(0)
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn message(result: Result<CellValue, String>) -> String {
+        match result {
+            Ok(CellValue::String(message)) => message,
+            other => panic!("expected a message, got {:?}", other.map(|v| v.to_string())),
+        }
+    }
+
+    #[test]
+    fn rewrite_query_quotes_paths_after_from_join_and_into() {
+        assert_eq!(rewrite_query("SELECT * FROM '/var/log'"), r#"SELECT * FROM "/var/log""#);
+        assert_eq!(
+            rewrite_query("SELECT * FROM ~/projects a JOIN ../sibling b ON a.name = b.name"),
+            r#"SELECT * FROM "~/projects" a JOIN "../sibling" b ON a.name = b.name"#
+        );
+        assert_eq!(rewrite_query("SELECT * FROM src/sub"), r#"SELECT * FROM "src/sub""#);
+        assert_eq!(rewrite_query("SELECT * FROM $HOME/notes;"), r#"SELECT * FROM "$HOME/notes";"#);
+        assert_eq!(rewrite_query("INSERT INTO ./out (relative_path) VALUES ('a')"), r#"INSERT INTO "./out" (relative_path) VALUES ('a')"#);
+        assert_eq!(rewrite_query(r#"SELECT * FROM '/a"b'"#), r#"SELECT * FROM "/a""b""#);
+    }
+
+    #[test]
+    fn rewrite_query_keeps_double_quoted_paths() {
+        for sql in [r#"SELECT * FROM "/tmp/t1/src""#, r#"SELECT * FROM "a b/c" JOIN "./d" ON 1 = 1"#] {
+            assert_eq!(rewrite_query(sql), sql);
+        }
+    }
+
+    #[test]
+    fn rewrite_query_keeps_plain_tables_and_strings() {
+        for sql in [
+            "SELECT * FROM files",
+            "SELECT * FROM tree('.', 2)",
+            "SELECT * FROM files WHERE name = 'FROM /etc'",
+            "SELECT * FROM files WHERE ext IS DISTINCT FROM '/x'",
+        ] {
+            assert_eq!(rewrite_query(sql), sql);
+        }
+    }
+
+    #[test]
+    fn rewrite_query_translates_glob() {
+        assert_eq!(
+            rewrite_query("SELECT * FROM files WHERE name GLOB '*.rs'"),
+            "SELECT * FROM files WHERE name OPERATOR(glob) '*.rs'"
+        );
+        assert_eq!(rewrite_query("SELECT * FROM files WHERE name = 'glob'"), "SELECT * FROM files WHERE name = 'glob'");
+    }
+
+    #[test]
+    fn like_patterns_match_the_whole_value() {
+        assert!(pattern_matches("src/%.rs", like(None), "src/a/b.rs"));
+        assert!(!pattern_matches("src/%.rs", like(None), "src/a.rsx"));
+        assert!(pattern_matches("a_c", like(None), "abc"));
+        assert!(!pattern_matches("a_c", like(None), "abbc"));
+        assert!(!pattern_matches("a.c", like(None), "abc"));
+        assert!(pattern_matches("%", like(None), "line one\nline two"));
+        assert!(!pattern_matches("README%", like(None), "readme.md"));
+        assert!(pattern_matches("README%", PatternKind::Like { case_insensitive: true, escape: None }, "readme.md"));
+    }
+
+    #[test]
+    fn like_patterns_honour_escape() {
+        assert!(pattern_matches("100\\%", like(Some('\\')), "100%"));
+        assert!(!pattern_matches("100\\%", like(Some('\\')), "1000"));
+        assert!(pattern_matches("a!_b", like(Some('!')), "a_b"));
+        assert!(!pattern_matches("a!_b", like(Some('!')), "axb"));
+    }
+
+    #[test]
+    fn glob_patterns_match_the_whole_value() {
+        assert!(pattern_matches("*.[ch]", PatternKind::Glob, "main.c"));
+        assert!(!pattern_matches("*.[ch]", PatternKind::Glob, "main.o"));
+        assert!(pattern_matches("[!.]*", PatternKind::Glob, "visible"));
+        assert!(!pattern_matches("[!.]*", PatternKind::Glob, ".hidden"));
+        assert!(pattern_matches("file?.txt", PatternKind::Glob, "file1.txt"));
+        assert!(!pattern_matches("*.RS", PatternKind::Glob, "main.rs"));
+        assert!(!pattern_matches("a+b", PatternKind::Glob, "aab"));
+    }
+
+    #[test]
+    fn rewrite_query_quotes_the_group_column() {
+        assert_eq!(
+            rewrite_query("SELECT group, COUNT(*) FROM files GROUP BY group"),
+            r#"SELECT "group", COUNT(*) FROM files GROUP BY "group""#
+        );
+    }
+
+    #[test]
+    fn parse_interval_reads_units() {
+        assert_eq!(parse_interval("7 days"), Some(TimeDelta::days(7)));
+        assert_eq!(parse_interval("1 hour 30 minutes"), Some(TimeDelta::minutes(90)));
+        assert_eq!(parse_interval("1.5h"), Some(TimeDelta::minutes(90)));
+        assert_eq!(parse_interval("2W"), Some(TimeDelta::weeks(2)));
+        assert_eq!(parse_interval("-1 day"), Some(TimeDelta::days(-1)));
+        assert_eq!(parse_interval("1 month"), Some(TimeDelta::days(30)));
+        assert_eq!(parse_interval(""), None);
+        assert_eq!(parse_interval("3 fortnights"), None);
+        assert_eq!(parse_interval("7 days ago"), None);
+    }
+
+    #[test]
+    fn strip_modifiers_reads_dry_run_and_cascade() {
+        let (query, options) = strip_modifiers("dry run DELETE FROM '/tmp/x' WHERE name = 'a' cascade;");
+        assert_eq!(query, "DELETE FROM '/tmp/x' WHERE name = 'a'");
+        assert!(options.dry_run);
+        assert!(options.cascade);
+        assert!(options.into_state.is_none());
+    }
+
+    #[test]
+    fn strip_modifiers_leaves_a_quoted_cascade_alone() {
+        let sql = "DELETE FROM files WHERE name = 'cascade'";
+        let (query, options) = strip_modifiers(sql);
+        assert_eq!(query, sql);
+        assert!(!options.cascade);
+    }
+
+    #[test]
+    fn delete_refuses_directories_without_cascade() {
+        let fixture = Fixture::new("delete-cascade", &["logs/a.log", "keep.txt"]);
+        let error = fixture.run("DELETE FROM tree('{root}') WHERE name = 'logs'").unwrap_err();
+        assert!(error.contains("Add CASCADE"), "{}", error);
+        assert!(fixture.path("logs/a.log").exists());
+    }
+
+    #[test]
+    fn dry_run_delete_leaves_files_alone() {
+        let fixture = Fixture::new("delete-dry-run", &["a.tmp", "sub/b.tmp", "c.txt"]);
+        let result = fixture.run("DRY RUN DELETE FROM tree('{root}') WHERE ext = 'tmp'");
+        assert_eq!(message(result), "Dry run: 2 entries would be deleted");
+        assert!(fixture.path("a.tmp").exists());
+        assert!(fixture.path("sub/b.tmp").exists());
+
+        let result = fixture.run("DRY RUN DELETE FROM tree('{root}') WHERE name = 'sub' CASCADE");
+        assert_eq!(message(result), "Dry run: 1 entries would be deleted");
+        assert!(fixture.path("sub/b.tmp").exists());
+    }
+
+    #[test]
+    fn delete_removes_children_before_their_parents() {
+        let fixture = Fixture::new("delete-order", &["a/b/c.txt", "a/d.txt", "a/e/", "f.txt"]);
+        let Statement::Delete(delete) = fixture.statement("DELETE FROM tree('{root}') WHERE relative_path LIKE 'a%'") else {
+            panic!("not a DELETE");
+        };
+        let targets = delete_targets(&FileSystemState::new(), &FavoritesManager::new().unwrap(), &delete).unwrap();
+        assert_eq!(targets.len(), 5);
+        assert!(targets.windows(2).all(|pair| pair[0]["depth"] >= pair[1]["depth"]));
+
+        // Had `a` gone first, its contents would be skipped as already deleted.
+        assert_eq!(message(remove_entries(&targets)), "Deleted 5 entries");
+        assert!(!fixture.path("a").exists());
+        assert!(fixture.path("f.txt").exists());
+    }
+
+    #[test]
+    fn parse_mode_reads_octal() {
+        assert_eq!(parse_mode(&TypedValue::Text("644".to_string())), Ok(0o644));
+        assert_eq!(parse_mode(&TypedValue::Text("0o750".to_string())), Ok(0o750));
+        assert_eq!(parse_mode(&TypedValue::Number(755.0)), Ok(0o755));
+        assert!(parse_mode(&TypedValue::Text("999".to_string())).is_err());
+        assert!(parse_mode(&TypedValue::Text("17777".to_string())).is_err());
+        assert!(parse_mode(&TypedValue::Text("rwxr-xr-x".to_string())).is_err());
+        assert!(parse_mode(&TypedValue::Null).is_err());
+    }
+
+    fn update_plan(fixture: &Fixture, sql: &str) -> Result<UpdatePlan, String> {
+        let Statement::Update(update) = fixture.statement(sql) else {
+            panic!("not an UPDATE: {}", sql);
+        };
+        plan_update(&FileSystemState::new(), &FavoritesManager::new().unwrap(), &update)
+    }
+
+    #[test]
+    fn dry_run_update_leaves_files_alone() {
+        let fixture = Fixture::new("update-dry-run", &["a.txt", "sub/b.txt"]);
+        let result = fixture.run("DRY RUN UPDATE tree('{root}') SET name = UPPER(name) WHERE ext = 'txt'");
+        assert_eq!(message(result), "Dry run: 2 entries would be updated");
+        assert!(fixture.path("a.txt").exists());
+        assert!(fixture.path("sub/b.txt").exists());
+    }
+
+    #[test]
+    fn update_refuses_the_whole_batch_on_collisions() {
+        let fixture = Fixture::new("update-collisions", &["a.txt", "b.txt", "c.md"]);
+        let error = fixture.run("UPDATE tree('{root}') SET name = 'same.txt' WHERE ext = 'txt'").unwrap_err();
+        assert!(error.contains("would both become"), "{}", error);
+
+        let error = fixture.run("UPDATE tree('{root}') SET name = 'c.md' WHERE name = 'a.txt'").unwrap_err();
+        assert!(error.contains("already exists"), "{}", error);
+        for name in ["a.txt", "b.txt", "c.md"] {
+            assert!(fixture.path(name).exists(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn update_renames_children_before_their_parents() {
+        let fixture = Fixture::new("update-order", &["src/lib/a.rs", "src/b.rs"]);
+        let plan = update_plan(&fixture, "UPDATE tree('{root}') SET name = UPPER(name)").unwrap();
+        assert_eq!(plan.changes.len(), 4);
+        assert_eq!(plan.changes.last().unwrap().from, fixture.path("src"));
+
+        assert_eq!(message(apply_updates(&plan.changes)), "Updated 4 entries");
+        assert!(fixture.path("SRC/LIB/A.RS").exists());
+        assert!(fixture.path("SRC/B.RS").exists());
+        assert!(!fixture.path("src").exists());
+    }
+
+    #[test]
+    fn update_moves_changes_extensions_and_modes() {
+        let fixture = Fixture::new("update-apply", &["a.log", "b.txt", "c.txt"]);
+        let plan = update_plan(&fixture, "UPDATE tree('{root}') SET parent = 'archive/2026' WHERE ext = 'log'").unwrap();
+        assert_eq!(message(apply_updates(&plan.changes)), "Updated 1 entries");
+        assert!(fixture.path("archive/2026/a.log").exists());
+
+        let plan = update_plan(&fixture, "UPDATE tree('{root}') SET ext = NULL, mode = '600' WHERE name = 'b.txt'").unwrap();
+        assert_eq!(message(apply_updates(&plan.changes)), "Updated 1 entries");
+        let meta = std::fs::metadata(fixture.path("b")).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+
+        let error = update_plan(&fixture, "UPDATE tree('{root}') SET ext = '.md' WHERE name = 'c.txt'").err().unwrap();
+        assert!(error.contains("Invalid extension"), "{}", error);
+        assert!(fixture.path("c.txt").exists());
+    }
+
+    #[test]
+    fn strip_modifiers_reads_into_state() {
+        let (query, options) = strip_modifiers("SELECT path FROM files INTO STATE");
+        assert_eq!(query, "SELECT path FROM files");
+        assert!(options.into_state == Some(IntoState::Replace));
+
+        let (query, options) = strip_modifiers("SELECT path FROM files into state append ;");
+        assert_eq!(query, "SELECT path FROM files");
+        assert!(options.into_state == Some(IntoState::Append));
+    }
+
+    #[test]
+    fn strip_modifiers_leaves_a_quoted_into_state_alone() {
+        let sql = "SELECT * FROM files WHERE name = 'into state'";
+        let (query, options) = strip_modifiers(sql);
+        assert_eq!(query, sql);
+        assert!(options.into_state.is_none());
+    }
+
+    /// The `name` column of a query's result, in row order.
+    fn names(result: Result<CellValue, String>) -> Vec<String> {
+        match result {
+            Ok(CellValue::Table(rows, _)) => rows.iter().map(|row| row["name"].to_string()).collect(),
+            other => panic!("expected a table, got {:?}", other.map(|v| v.to_string())),
+        }
+    }
+
+    #[test]
+    fn subquery_results_keep_their_type() {
+        let scope = Scope { row: &Row::new(), group: None };
+        let modified = TypedValue::Timestamp(Local.timestamp_nanos(1_767_225_600_123_456_789));
+        let age = TypedValue::Interval(TimeDelta::nanoseconds(-90_061_000_000_001));
+        for value in [modified, age, TypedValue::Text("2026-01-01".to_string()), TypedValue::Number(1.5), TypedValue::Null] {
+            assert_eq!(eval_value(&literal(value.clone()), &scope), Ok(value));
+        }
+    }
+
+    #[test]
+    fn scalar_subquery_matches_the_newest_timestamp() {
+        let fixture = Fixture::new("subquery-max", &["old.txt", "new.txt", "mid.txt"]);
+        // Sub-second parts, so that dropping them would lose the match.
+        for (name, nanos) in [("old.txt", 100_000_001), ("mid.txt", 200_000_002), ("new.txt", 300_000_003)] {
+            let time = std::time::UNIX_EPOCH + std::time::Duration::new(1_767_225_600, nanos);
+            std::fs::File::options().write(true).open(fixture.path(name)).unwrap().set_modified(time).unwrap();
+        }
+        let result = fixture.run("SELECT name FROM tree('{root}') WHERE modified = (SELECT MAX(modified) FROM tree('{root}'))");
+        assert_eq!(names(result), vec!["new.txt"]);
+
+        let result = fixture.run(
+            "SELECT name FROM tree('{root}') WHERE modified - (SELECT MIN(modified) FROM tree('{root}')) = \
+             (SELECT MAX(modified) - MIN(modified) FROM tree('{root}'))",
+        );
+        assert_eq!(names(result), vec!["new.txt"]);
+    }
+
+    #[test]
+    fn pushdown_limits_depth() {
+        assert_eq!(Pushdown::from_where(Some(&where_clause("depth <= 2"))).max_depth, Some(2));
+        assert_eq!(Pushdown::from_where(Some(&where_clause("depth < 2.5"))).max_depth, Some(2));
+        assert_eq!(Pushdown::from_where(Some(&where_clause("3 > depth"))).max_depth, Some(2));
+        assert_eq!(Pushdown::from_where(Some(&where_clause("depth BETWEEN 1 AND 4 AND depth = 3"))).max_depth, Some(3));
+        assert_eq!(Pushdown::from_where(Some(&where_clause("(depth <= 1 AND size > 0)"))).max_depth, Some(1));
+        // Neither side of an OR has to hold, and NOT BETWEEN excludes rather than bounds.
+        assert_eq!(Pushdown::from_where(Some(&where_clause("depth <= 1 OR name = 'a'"))).max_depth, None);
+        assert_eq!(Pushdown::from_where(Some(&where_clause("depth NOT BETWEEN 1 AND 4"))).max_depth, None);
+        assert_eq!(Pushdown::from_where(Some(&where_clause("depth >= 2"))).max_depth, None);
+    }
+
+    #[test]
+    fn pushdown_takes_path_prefixes() {
+        let pushdown = Pushdown::from_where(Some(&where_clause("relative_path LIKE 'src/%' AND path GLOB '/srv/*/logs'")));
+        let mut prefixes = pushdown.prefixes.clone();
+        prefixes.sort();
+        assert_eq!(prefixes, vec![("path", "/srv/".to_string()), ("relative_path", "src/".to_string())]);
+
+        let pushdown = Pushdown::from_where(Some(&where_clause("relative_path = 'src/main.rs'")));
+        assert_eq!(pushdown.prefixes, vec![("relative_path", "src/main.rs".to_string())]);
+
+        for condition in [
+            "relative_path NOT LIKE 'src/%'",
+            "relative_path LIKE '%.rs'",
+            "name LIKE 'src/%'",
+            "relative_path LIKE 'src/%' OR depth = 1",
+        ] {
+            assert!(Pushdown::from_where(Some(&where_clause(condition))).prefixes.is_empty(), "{}", condition);
+        }
+    }
+
+    #[test]
+    fn pushdown_prunes_directories_outside_the_prefix() {
+        let root = Path::new("/r");
+        let pushdown = Pushdown::from_where(Some(&where_clause("relative_path LIKE 'src/ma%'")));
+        assert!(pushdown.may_contain(Path::new("/r/src"), root));
+        assert!(pushdown.may_contain(Path::new("/r/src/main"), root));
+        assert!(!pushdown.may_contain(Path::new("/r/srcx"), root));
+        assert!(!pushdown.may_contain(Path::new("/r/docs"), root));
+        assert!(!pushdown.may_contain(Path::new("/r/src/lib"), root));
+
+        let pushdown = Pushdown::from_where(Some(&where_clause("path GLOB '/srv/www/*'")));
+        assert!(pushdown.may_contain(Path::new("/srv"), root));
+        assert!(pushdown.may_contain(Path::new("/srv/www/a"), root));
+        assert!(!pushdown.may_contain(Path::new("/var"), root));
     }
 }
@@ -0,0 +1,260 @@
+// Tables over the shell's own state and the running system, e.g. `FROM processes`.
+// The SQL engine turns their rows into a table; the readers of /proc, statvfs and
+// the account files live here so sql_engine.rs only deals with queries.
+
+use crate::favorites::FavoritesManager;
+use crate::file_system_state::FileSystemState;
+use crate::sql_engine::TypedValue;
+use chrono::{Local, TimeDelta};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::os::unix::fs::MetadataExt;
+
+/// Quote the name (`FROM './env'`) to read a directory with the same name instead.
+pub(crate) const SYSTEM_TABLES: &[&str] =
+    &["processes", "mounts", "env", "aliases", "interactive", "favorites", "jobs", "history", "views"];
+
+/// The columns of a system table, known without reading it.
+pub(crate) fn columns(name: &str) -> Option<&'static [&'static str]> {
+    Some(match name {
+        "processes" => &["pid", "ppid", "name", "state", "owner", "uid", "threads", "cpu", "mem", "rss", "vsz", "started", "command"],
+        "mounts" => &["device", "path", "fs_type", "options", "size", "used", "available", "use_percent"],
+        "env" => &["name", "value"],
+        "aliases" => &["name", "command"],
+        "interactive" => &["command"],
+        // `id` is the index that `RUN FAV` and `FAV RM` take.
+        "favorites" => &["id", "name", "path"],
+        "jobs" => &["id", "command", "pid"],
+        "history" => &["id", "command"],
+        // `columns` is NULL unless the view renames them.
+        "views" => &["name", "columns", "query"],
+        _ => return None,
+    })
+}
+
+/// The rows of a system table, with values in `columns` order.
+pub(crate) fn rows(name: &str, sys_state: &FileSystemState, favorites: &FavoritesManager) -> Result<Vec<Vec<TypedValue>>, String> {
+    let text = |s: &str| TypedValue::Text(s.to_string());
+    Ok(match name {
+        "processes" => read_processes(),
+        "mounts" => read_mounts()?,
+        "env" => {
+            let mut vars: Vec<(String, String)> = std::env::vars_os()
+                .map(|(k, v)| (k.to_string_lossy().to_string(), v.to_string_lossy().to_string()))
+                .collect();
+            vars.sort();
+            vars.iter().map(|(k, v)| vec![text(k), text(v)]).collect()
+        }
+        "aliases" => {
+            let mut aliases: Vec<(&String, &String)> = sys_state.aliases.iter().collect();
+            aliases.sort();
+            aliases.into_iter().map(|(k, v)| vec![text(k), text(v)]).collect()
+        }
+        "interactive" => sys_state.interactive_commands.iter().map(|c| vec![text(c)]).collect(),
+        "favorites" => favorites
+            .get_all()
+            .iter()
+            .enumerate()
+            .map(|(i, f)| vec![TypedValue::Number(i as f64), text(f.get_alias_name()), text(&f.get_path().to_string_lossy())])
+            .collect(),
+        "jobs" => {
+            let registry = crate::jobs::JOB_REGISTRY.lock().map_err(|_| "Job registry is unavailable".to_string())?;
+            registry
+                .iter()
+                .map(|job| {
+                    // A job whose child is busy being awaited still shows up, just without a pid.
+                    let pid = job.child.try_lock().ok().and_then(|child| child.id());
+                    vec![
+                        TypedValue::Number(job.id as f64),
+                        text(&job.command),
+                        pid.map_or(TypedValue::Null, |p| TypedValue::Number(p as f64)),
+                    ]
+                })
+                .collect()
+        }
+        "history" => sys_state
+            .history
+            .iter()
+            .enumerate()
+            .map(|(i, line)| vec![TypedValue::Number((i + 1) as f64), text(line)])
+            .collect(),
+        // Queries are kept as sqlparser reads them, so GLOB is shown the way it was written.
+        "views" => crate::views::load()?
+            .into_iter()
+            .map(|(name, view)| {
+                let columns = if view.columns.is_empty() { TypedValue::Null } else { text(&view.columns.join(", ")) };
+                vec![text(&name), columns, text(&view.query.replace("OPERATOR(glob)", "GLOB"))]
+            })
+            .collect(),
+        _ => return Err(format!("Unsupported table '{}'", name)),
+    })
+}
+
+/// One row per process in /proc. `cpu` is the share of one core used since the
+/// process started and `mem` the share of physical memory, both in percent like
+/// `ps`; `rss` and `vsz` are in KB.
+fn read_processes() -> Vec<Vec<TypedValue>> {
+    // SAFETY: sysconf only reads system configuration values.
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as f64;
+    let page_kb = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(1024) as f64 / 1024.0;
+    let uptime: f64 = std::fs::read_to_string("/proc/uptime")
+        .ok()
+        .and_then(|s| s.split_whitespace().next()?.parse().ok())
+        .unwrap_or(0.0);
+    let booted = Local::now() - TimeDelta::milliseconds((uptime * 1000.0) as i64);
+    let mem_total_kb: f64 = std::fs::read_to_string("/proc/meminfo")
+        .ok()
+        .and_then(|s| s.lines().find_map(|l| l.strip_prefix("MemTotal:")?.split_whitespace().next()?.parse().ok()))
+        .unwrap_or(0.0);
+    let percent = |part: f64, whole: f64| if whole > 0.0 { (part / whole * 1000.0).round() / 10.0 } else { 0.0 };
+
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    let mut processes = Vec::new();
+    for entry in entries.flatten() {
+        let Ok(pid) = entry.file_name().to_string_lossy().parse::<u32>() else {
+            continue;
+        };
+        let dir = entry.path();
+        // The process may exit while we read it; skip it then.
+        let (Ok(stat), Ok(meta)) = (std::fs::read_to_string(dir.join("stat")), std::fs::metadata(&dir)) else {
+            continue;
+        };
+        // The name is in parentheses and may itself contain spaces or ')'.
+        let (Some(open), Some(close)) = (stat.find('('), stat.rfind(')')) else {
+            continue;
+        };
+        let fields: Vec<&str> = stat[close + 1..].split_whitespace().collect();
+        if fields.len() < 22 {
+            continue;
+        }
+        let field = |i: usize| fields[i].parse::<f64>().unwrap_or(0.0);
+        let cpu_seconds = (field(11) + field(12)) / ticks;
+        let started_after = field(19) / ticks;
+        let rss = field(21) * page_kb;
+        let command = std::fs::read(dir.join("cmdline"))
+            .map(|bytes| String::from_utf8_lossy(&bytes).replace('\0', " ").trim().to_string())
+            .unwrap_or_default();
+
+        processes.push(vec![
+            TypedValue::Number(pid as f64),
+            TypedValue::Number(field(1)),
+            TypedValue::Text(stat[open + 1..close].to_string()),
+            TypedValue::Text(fields[0].to_string()),
+            TypedValue::Text(user_name(meta.uid())),
+            TypedValue::Number(meta.uid() as f64),
+            TypedValue::Number(field(17)),
+            TypedValue::Number(percent(cpu_seconds, uptime - started_after)),
+            TypedValue::Number(percent(rss, mem_total_kb)),
+            TypedValue::Number(rss),
+            TypedValue::Number((field(20) / 1024.0).round()),
+            TypedValue::Timestamp(booted + TimeDelta::milliseconds((started_after * 1000.0) as i64)),
+            // Kernel threads have no command line.
+            if command.is_empty() { TypedValue::Null } else { TypedValue::Text(command) },
+        ]);
+    }
+    processes
+}
+
+/// One row per entry of /proc/mounts, with sizes in bytes from statvfs. Like
+/// `df`, `use_percent` counts only the space available to unprivileged users.
+fn read_mounts() -> Result<Vec<Vec<TypedValue>>, String> {
+    let mounts = std::fs::read_to_string("/proc/mounts").map_err(|e| format!("Cannot read /proc/mounts: {}", e))?;
+    let mut rows = Vec::new();
+    for line in mounts.lines() {
+        let fields: Vec<String> = line.split_whitespace().map(unescape_mount_field).collect();
+        let [device, path, fs_type, options, ..] = fields.as_slice() else {
+            continue;
+        };
+        let (size, used, available) = match statvfs(path) {
+            Some((size, used, available)) => (TypedValue::Number(size), TypedValue::Number(used), TypedValue::Number(available)),
+            None => (TypedValue::Null, TypedValue::Null, TypedValue::Null),
+        };
+        let use_percent = match (&used, &available) {
+            (TypedValue::Number(used), TypedValue::Number(available)) if used + available > 0.0 => {
+                TypedValue::Number((used / (used + available) * 100.0).round())
+            }
+            _ => TypedValue::Null,
+        };
+        rows.push(vec![
+            TypedValue::Text(device.clone()),
+            TypedValue::Text(path.clone()),
+            TypedValue::Text(fs_type.clone()),
+            TypedValue::Text(options.clone()),
+            size,
+            used,
+            available,
+            use_percent,
+        ]);
+    }
+    Ok(rows)
+}
+
+/// /proc/mounts writes spaces and other separators in paths as octal escapes, e.g. `\040`.
+fn unescape_mount_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\'
+            && let Some(code) = field.get(i + 1..i + 4).and_then(|oct| u8::from_str_radix(oct, 8).ok())
+        {
+            out.push(code);
+            i += 4;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Total, used and available bytes of the filesystem mounted at `path`.
+fn statvfs(path: &str) -> Option<(f64, f64, f64)> {
+    let c_path = std::ffi::CString::new(path).ok()?;
+    // SAFETY: `stats` is plain old data filled in by statvfs, and `c_path` is a
+    // NUL-terminated string that outlives the call.
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stats) } != 0 {
+        return None;
+    }
+    let block = stats.f_frsize as f64;
+    let size = stats.f_blocks as f64 * block;
+    let free = stats.f_bfree as f64 * block;
+    Some((size, size - free, stats.f_bavail as f64 * block))
+}
+
+lazy_static! {
+    /// uid -> user name, read once from /etc/passwd.
+    static ref USER_NAMES: HashMap<u32, String> = read_account_names("/etc/passwd");
+    /// gid -> group name, read once from /etc/group.
+    static ref GROUP_NAMES: HashMap<u32, String> = read_account_names("/etc/group");
+}
+
+/// Parses `name:password:id:...` lines; both /etc/passwd and /etc/group use that layout.
+fn read_account_names(file: &str) -> HashMap<u32, String> {
+    std::fs::read_to_string(file)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((id, name.to_string()))
+        })
+        .collect()
+}
+
+/// Falls back to the numeric id when the account has no name (e.g. files from another machine).
+fn account_name(names: &HashMap<u32, String>, id: u32) -> String {
+    names.get(&id).cloned().unwrap_or_else(|| id.to_string())
+}
+
+pub(crate) fn user_name(uid: u32) -> String {
+    account_name(&USER_NAMES, uid)
+}
+
+pub(crate) fn group_name(gid: u32) -> String {
+    account_name(&GROUP_NAMES, gid)
+}
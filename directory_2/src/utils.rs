@@ -4,6 +4,8 @@ use lazy_static::lazy_static;
 
 lazy_static! {
     pub static ref OUTPUT_LOG: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    /// Fixed on first use (at startup) so that CD does not move it.
+    static ref CONFIG_DIR: std::path::PathBuf = std::env::current_dir().unwrap_or_default().join(".directory_2");
 }
 
 pub static CLEAR_MARKER: AtomicUsize = AtomicUsize::new(0);
@@ -74,3 +76,9 @@ macro_rules! cprint {
         print!($($arg)*);
     }};
 }
+
+/// The folder favorites and saved views are kept in: .directory_2 in the
+/// directory dir2 was started from.
+pub fn config_dir() -> std::path::PathBuf {
+    CONFIG_DIR.clone()
+}
@@ -6,7 +6,9 @@ use comfy_table::presets::UTF8_FULL;
 pub enum Value {
     String(String),
     Record(HashMap<String, Value>),
-    Table(Vec<HashMap<String, Value>>),
+    /// Rows, and the order to show their columns in. With no order given the
+    /// columns are sorted by name.
+    Table(Vec<HashMap<String, Value>>, Vec<String>),
     Boolean(bool),
     Integer(i64),
     Float(f64),
@@ -37,7 +39,7 @@ impl Value {
                 }
                 table.to_string()
             }
-            Value::Table(rows) => {
+            Value::Table(rows, columns) => {
                 if rows.is_empty() {
                     return "(empty table)".to_string();
                 }
@@ -58,6 +60,9 @@ impl Value {
                     .into_iter()
                     .collect();
                 keys.sort(); // Sort keys for consistent columns
+                if !columns.is_empty() {
+                    keys = columns.clone();
+                }
                 
                 let mut header_cells = Vec::new();
                 for k in &keys {
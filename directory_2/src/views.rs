@@ -0,0 +1,70 @@
+// Saved SQL queries: `CREATE VIEW name AS SELECT ...` stores the SELECT in
+// views.json next to favorites.json, and `FROM name` runs it again.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct View {
+    /// The SELECT, as SQL.
+    pub query: String,
+    /// The names given in `CREATE VIEW name (a, b) AS ...`, if any.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<String>,
+}
+
+fn views_file() -> PathBuf {
+    crate::utils::config_dir().join("views.json")
+}
+
+/// All saved views by lowercase name. A missing file means no views yet.
+pub fn load() -> Result<BTreeMap<String, View>, String> {
+    let file_path = views_file();
+    if !file_path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = fs::read_to_string(&file_path).map_err(|e| format!("Failed to read views file: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse views JSON: {}", e))
+}
+
+fn save(views: &BTreeMap<String, View>) -> Result<(), String> {
+    let file_path = views_file();
+    let json = serde_json::to_string_pretty(views).map_err(|e| format!("Failed to serialize views: {}", e))?;
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+    }
+    fs::write(&file_path, json).map_err(|e| format!("Failed to write views file: {}", e))
+}
+
+pub fn get(name: &str) -> Result<Option<View>, String> {
+    Ok(load()?.remove(&name.to_lowercase()))
+}
+
+pub fn exists(name: &str) -> bool {
+    get(name).is_ok_and(|view| view.is_some())
+}
+
+/// Saves a view. Returns false, without changing anything, when one with that
+/// name exists and `replace` is not set.
+pub fn create(name: &str, view: View, replace: bool) -> Result<bool, String> {
+    let mut views = load()?;
+    let name = name.to_lowercase();
+    if views.contains_key(&name) && !replace {
+        return Ok(false);
+    }
+    views.insert(name, view);
+    save(&views)?;
+    Ok(true)
+}
+
+/// Deletes a view. Returns false when there was none with that name.
+pub fn remove(name: &str) -> Result<bool, String> {
+    let mut views = load()?;
+    if views.remove(&name.to_lowercase()).is_none() {
+        return Ok(false);
+    }
+    save(&views)?;
+    Ok(true)
+}
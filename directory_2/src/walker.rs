@@ -0,0 +1,333 @@
+// Parallel directory traversal shared by the SQL engine and the indexer.
+//
+// Directories are jobs on work-stealing deques: each worker reads its own
+// directories depth-first and steals from the others when it runs dry, so one
+// huge subtree does not leave the rest of the pool idle. The pool size comes
+// from DIR2_THREADS (e.g. `EXPORT DIR2_THREADS=4` in ~/.dir2rc) and defaults to
+// the number of CPUs. Ctrl-C stops the walk instead of killing the shell.
+
+use std::ffi::OsStr;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+use crossbeam_deque::{Injector, Steal, Stealer, Worker};
+
+/// What a walk is stopped with when Ctrl-C is pressed.
+pub const CANCELLED: &str = "Cancelled";
+
+pub struct WalkOptions {
+    /// Entries shallower than this are read but not returned; 0 includes the root.
+    pub min_depth: usize,
+    pub max_depth: Option<usize>,
+    pub follow_links: bool,
+}
+
+/// An entry found by `walk`, with the same accessors as `walkdir::DirEntry`.
+pub struct Entry {
+    path: PathBuf,
+    depth: usize,
+    file_type: fs::FileType,
+    path_is_symlink: bool,
+    follow: bool,
+}
+
+impl Entry {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn file_name(&self) -> &OsStr {
+        self.path.file_name().unwrap_or(self.path.as_os_str())
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The type of the entry, or of its target when links are followed.
+    pub fn file_type(&self) -> fs::FileType {
+        self.file_type
+    }
+
+    pub fn path_is_symlink(&self) -> bool {
+        self.path_is_symlink
+    }
+
+    /// The entry's own metadata, or its target's when links are followed.
+    pub fn metadata(&self) -> std::io::Result<fs::Metadata> {
+        if self.follow { fs::metadata(&self.path) } else { fs::symlink_metadata(&self.path) }
+    }
+}
+
+struct Job {
+    entry: Entry,
+    /// (device, inode) of the directories above, to stop symlink loops.
+    ancestors: Vec<(u64, u64)>,
+}
+
+/// Number of worker threads: DIR2_THREADS, or one per CPU.
+pub fn threads() -> usize {
+    std::env::var("DIR2_THREADS")
+        .ok()
+        .and_then(|n| n.trim().parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+}
+
+/// Walks `root` on `threads()` workers. `filter` works like walkdir's
+/// `filter_entry`: an entry it rejects is neither returned nor descended into.
+/// `map` runs on the workers too, so per-entry work such as stat-ing or hashing
+/// is spread across them. Results come back in depth-first order sorted by name,
+/// whatever order the workers finished in. Unreadable entries are skipped.
+pub fn walk<T, F, M>(root: &Path, options: &WalkOptions, filter: F, map: M) -> Result<Vec<T>, String>
+where
+    T: Send,
+    F: Fn(&Entry) -> bool + Sync,
+    M: Fn(&Entry) -> Option<T> + Sync,
+{
+    let _interrupts = catch_interrupts();
+    let Ok(root_meta) = fs::symlink_metadata(root) else {
+        return Ok(Vec::new());
+    };
+    // Like walkdir, a symlink given as the root itself is always followed.
+    let file_type = fs::metadata(root).map_or(root_meta.file_type(), |m| m.file_type());
+    let root_entry = Entry {
+        path: root.to_path_buf(),
+        depth: 0,
+        file_type,
+        path_is_symlink: root_meta.file_type().is_symlink(),
+        follow: options.follow_links,
+    };
+
+    if !filter(&root_entry) {
+        return Ok(Vec::new());
+    }
+    let mut results = Vec::new();
+    if options.min_depth == 0
+        && let Some(value) = map(&root_entry)
+    {
+        results.push((root_entry.path.clone(), value));
+    }
+    if !root_entry.file_type.is_dir() || options.max_depth == Some(0) {
+        return Ok(results.into_iter().map(|(_, value)| value).collect());
+    }
+
+    let ancestors = match (options.follow_links, fs::metadata(root)) {
+        (true, Ok(m)) => vec![(m.dev(), m.ino())],
+        _ => Vec::new(),
+    };
+    let injector = Injector::new();
+    injector.push(Job { entry: root_entry, ancestors });
+    let pending = AtomicUsize::new(1);
+    // One directory level needs no helpers.
+    let threads = if options.max_depth == Some(1) { 1 } else { threads() };
+    let locals: Vec<Worker<Job>> = (0..threads).map(|_| Worker::new_lifo()).collect();
+    let stealers: Vec<Stealer<Job>> = locals.iter().map(|w| w.stealer()).collect();
+    let idle = Idle { lock: Mutex::new(()), wake: Condvar::new(), sleepers: AtomicUsize::new(0) };
+    let pool = Pool { options, filter: &filter, map: &map, injector: &injector, stealers: &stealers, pending: &pending, idle: &idle };
+
+    let found = if threads == 1 {
+        let local = locals.into_iter().next().expect("one worker");
+        vec![pool.work(&local)]
+    } else {
+        std::thread::scope(|scope| {
+            let pool = &pool;
+            let handles: Vec<_> = locals.into_iter().map(|local| scope.spawn(move || pool.work(&local))).collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+                .collect()
+        })
+    };
+    if interrupted() {
+        return Err(CANCELLED.to_string());
+    }
+    results.extend(found.into_iter().flatten());
+    // Path ordering compares component by component, which gives depth-first order.
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(results.into_iter().map(|(_, value)| value).collect())
+}
+
+struct Pool<'a, F, M> {
+    options: &'a WalkOptions,
+    filter: &'a F,
+    map: &'a M,
+    injector: &'a Injector<Job>,
+    stealers: &'a [Stealer<Job>],
+    /// Directories queued or being read; the walk is over when it reaches 0.
+    pending: &'a AtomicUsize,
+    idle: &'a Idle,
+}
+
+/// Where workers with nothing to steal sleep until another one queues
+/// directories or the walk ends, instead of spinning.
+struct Idle {
+    lock: Mutex<()>,
+    wake: Condvar,
+    /// Workers waiting on `wake`, so busy ones only notify when someone listens.
+    sleepers: AtomicUsize,
+}
+
+impl<T, F, M> Pool<'_, F, M>
+where
+    T: Send,
+    F: Fn(&Entry) -> bool + Sync,
+    M: Fn(&Entry) -> Option<T> + Sync,
+{
+    fn work(&self, local: &Worker<Job>) -> Vec<(PathBuf, T)> {
+        let mut found = Vec::new();
+        loop {
+            if interrupted() {
+                break;
+            }
+            match self.next_job(local) {
+                Some(job) => {
+                    let queued = self.read_dir(job, local, &mut found);
+                    let last = self.pending.fetch_sub(1, Ordering::SeqCst) == 1;
+                    // A lone subdirectory is popped by this worker itself; more can be stolen.
+                    if last || queued > 1 {
+                        self.wake_idle();
+                    }
+                }
+                None if self.pending.load(Ordering::SeqCst) == 0 => break,
+                None => self.sleep(),
+            }
+        }
+        found
+    }
+
+    fn sleep(&self) {
+        let guard = self.idle.lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.idle.sleepers.fetch_add(1, Ordering::SeqCst);
+        // Checked after registering, so work queued meanwhile is either seen here
+        // or its notify finds this worker waiting.
+        let work = self.pending.load(Ordering::SeqCst) == 0
+            || !self.injector.is_empty()
+            || self.stealers.iter().any(|s| !s.is_empty());
+        if !work {
+            // The timeout keeps Ctrl-C responsive.
+            let _ = self.idle.wake.wait_timeout(guard, Duration::from_millis(50));
+        }
+        self.idle.sleepers.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    fn wake_idle(&self) {
+        if self.idle.sleepers.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        // Taking the lock orders this after any sleeper's check of the queues.
+        drop(self.idle.lock.lock().unwrap_or_else(|e| e.into_inner()));
+        self.idle.wake.notify_all();
+    }
+
+    fn next_job(&self, local: &Worker<Job>) -> Option<Job> {
+        local.pop().or_else(|| {
+            std::iter::repeat_with(|| {
+                self.injector
+                    .steal_batch_and_pop(local)
+                    .or_else(|| self.stealers.iter().map(|s| s.steal()).collect::<Steal<Job>>())
+            })
+            .find(|steal| !steal.is_retry())
+            .and_then(|steal| steal.success())
+        })
+    }
+
+    /// Returns how many subdirectories it queued.
+    fn read_dir(&self, job: Job, local: &Worker<Job>, found: &mut Vec<(PathBuf, T)>) -> usize {
+        let Ok(children) = fs::read_dir(&job.entry.path) else {
+            return 0;
+        };
+        let mut queued = 0;
+        let depth = job.entry.depth + 1;
+        for child in children.flatten() {
+            let Ok(own_type) = child.file_type() else {
+                continue;
+            };
+            let path = child.path();
+            let is_symlink = own_type.is_symlink();
+            // A broken link stays a link.
+            let file_type = if is_symlink && self.options.follow_links {
+                fs::metadata(&path).map_or(own_type, |m| m.file_type())
+            } else {
+                own_type
+            };
+            let entry = Entry { path, depth, file_type, path_is_symlink: is_symlink, follow: self.options.follow_links };
+            if !(self.filter)(&entry) {
+                continue;
+            }
+            if depth >= self.options.min_depth
+                && let Some(value) = (self.map)(&entry)
+            {
+                found.push((entry.path.clone(), value));
+            }
+            if !file_type.is_dir() || self.options.max_depth.is_some_and(|max| depth >= max) {
+                continue;
+            }
+            let mut ancestors = Vec::new();
+            if self.options.follow_links {
+                let Ok(m) = fs::metadata(&entry.path) else {
+                    continue;
+                };
+                if job.ancestors.contains(&(m.dev(), m.ino())) {
+                    continue;
+                }
+                ancestors = job.ancestors.clone();
+                ancestors.push((m.dev(), m.ino()));
+            }
+            self.pending.fetch_add(1, Ordering::SeqCst);
+            local.push(Job { entry, ancestors });
+            queued += 1;
+        }
+        queued
+    }
+}
+
+// ---------------------CTRL-C------------------------
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static GUARDS: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn on_interrupt(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// While alive, Ctrl-C sets `interrupted()` instead of killing the shell.
+pub struct InterruptGuard {
+    previous: libc::sigaction,
+}
+
+pub fn catch_interrupts() -> InterruptGuard {
+    if GUARDS.fetch_add(1, Ordering::SeqCst) == 0 {
+        INTERRUPTED.store(false, Ordering::SeqCst);
+    }
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe,
+    // and both sigaction structs are plain data owned by this frame.
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_interrupt as *const () as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        let mut previous: libc::sigaction = std::mem::zeroed();
+        libc::sigaction(libc::SIGINT, &action, &mut previous);
+        InterruptGuard { previous }
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        // SAFETY: restores the handler saved when the guard was made.
+        unsafe {
+            libc::sigaction(libc::SIGINT, &self.previous, std::ptr::null_mut());
+        }
+        GUARDS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Whether Ctrl-C was pressed since the outermost `catch_interrupts`.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}